@@ -0,0 +1,537 @@
+use std::{
+    pin::Pin,
+    str::FromStr,
+    sync::{atomic::Ordering, Arc},
+    task::{Context, Poll},
+};
+
+use futures_util::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, Stream, StreamExt,
+};
+use serde_json::json;
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::{sleep_until, Instant},
+};
+use tokio_tungstenite::tungstenite::{
+    Error as WsError,
+    Message::{self, Text},
+};
+
+use crate::{
+    const_vars::gen_chat_hub_wss_url,
+    types::{
+        bot_answer_type::BotAnswer,
+        bot_easy_resp_type::BotResp,
+        chat_timeout_type::{ChatTimeouts, TimeoutPhase},
+        chat_type::Chat,
+        error_type::BingError,
+        locale_type::Locale,
+        stop_outcome_type::{StopOutcome, StopStatus},
+        user_input_type::UserInput,
+    },
+    utils::{
+        connect_ws::{build_chat_hub_request, connect_chat_hub, ChatHubStream},
+        msg_proces::add_suffix,
+        process_bot_resp::{json2bot_resp_type1, json2bot_resp_type2, DrawTask, TextTracker},
+        stop_signal::{StopHandle, StopSignal},
+    },
+};
+
+use super::BingClient;
+
+pub(crate) type ChatHubWrite = SplitSink<ChatHubStream, Message>;
+pub(crate) type ChatHubRead = SplitStream<ChatHubStream>;
+pub(crate) type Connection = (ChatHubWrite, ChatHubRead);
+
+// How `read_answer` left the connection
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum AnswerEnd {
+    // The answer is complete, the connection can take another invocation
+    Reusable,
+    Closed,
+    // The connection ended before any frame of the answer, nothing was sent to `tx`
+    Unanswered,
+}
+
+const ASK_ACTION: &str = "Ask Bing Copilot";
+
+// How many responses are buffered before the websocket is read again
+pub(crate) const CHANNEL_SIZE: usize = 32;
+
+// An answer that owns its client and chat, so it can be moved into `tokio::spawn`.
+// The websocket is read by a spawned task, dropping the stream stops the answer.
+pub struct AskStream {
+    rx: mpsc::Receiver<Result<BotResp, BingError>>,
+    stop_handle: StopHandle,
+}
+
+impl AskStream {
+    // A chat at the message limit is rolled over first, the stream starts with `BotResp::Rollover`
+    pub async fn new(
+        client: Arc<BingClient>,
+        chat: Arc<Chat>,
+        mut user_input: UserInput,
+    ) -> Result<AskStream, BingError> {
+        let rollover = client.rollover_question(&chat, &mut user_input).await?;
+        let chat = rollover
+            .as_ref()
+            .map_or(chat, |rollover| rollover.chat.clone());
+        let mut connection = client.open_chat_hub(&chat, &user_input).await?;
+        let mut signal = StopSignal::new();
+        let stop_handle = signal.handle();
+        let (tx, rx) = mpsc::channel(CHANNEL_SIZE);
+        tokio::spawn(async move {
+            if let Some(rollover) = rollover {
+                let _ = tx.send(Ok(BotResp::Rollover(rollover))).await;
+            }
+            client
+                .read_answer(&chat, &mut connection, &user_input, &mut signal, tx, false)
+                .await;
+        });
+        Ok(AskStream { rx, stop_handle })
+    }
+
+    // An answer read by someone else, e.g. a `ChatSession`
+    pub(crate) fn from_channel(
+        rx: mpsc::Receiver<Result<BotResp, BingError>>,
+        stop_handle: StopHandle,
+    ) -> AskStream {
+        AskStream { rx, stop_handle }
+    }
+
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop_handle.clone()
+    }
+
+    pub async fn stop(&self) -> StopOutcome {
+        self.stop_handle.stop().await
+    }
+
+    // Waits for the rest of the answer, like `BingClient::ask`.
+    // Its `finish` is `Stopped` if `stop` was called.
+    pub async fn answer(mut self) -> Result<BotAnswer, BingError> {
+        let mut answer = BotAnswer::default();
+        let mut error = None;
+        while let Some(item) = self.next().await {
+            answer.push_item(item, &mut error);
+        }
+        answer.finish(error, self.stop_handle.is_stopped())
+    }
+}
+
+impl Stream for AskStream {
+    type Item = Result<BotResp, BingError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl BingClient {
+    // Connects to the ChatHub of `chat` and sends `user_input`
+    pub(crate) async fn open_chat_hub(
+        &self,
+        chat: &Chat,
+        user_input: &UserInput,
+    ) -> Result<Connection, BingError> {
+        let (mut write, read) = self
+            .handshake_chat_hub(chat, user_input.locale.as_ref())
+            .await?;
+        write.send(Text(serde_json::to_string(user_input)?)).await?;
+        Ok((write, read))
+    }
+
+    // Connects to the ChatHub of `chat`, ready for invocations.
+    // A rejected signature is fetched again once.
+    pub(crate) async fn handshake_chat_hub(
+        &self,
+        chat: &Chat,
+        locale: Option<&Locale>,
+    ) -> Result<Connection, BingError> {
+        self.ensure_chat_signature(chat).await?;
+        match self.handshake_with_signature(chat, locale).await {
+            Err(e) if e.is_signature_rejected() => {
+                self.update_chat_signature(chat).await?;
+                self.handshake_with_signature(chat, locale).await
+            }
+            rst => rst,
+        }
+    }
+
+    async fn handshake_with_signature(
+        &self,
+        chat: &Chat,
+        locale: Option<&Locale>,
+    ) -> Result<Connection, BingError> {
+        let url = gen_chat_hub_wss_url(
+            &self.config.endpoints,
+            chat.x_sydney_encryptedconversationsignature
+                .read()
+                .await
+                .as_ref()
+                .unwrap(),
+        );
+
+        let request = build_chat_hub_request(&self.config, &url, &self.cookie_jar, locale)?;
+        let handshake = async {
+            let ws_stram = connect_chat_hub(&self.config, request, &self.cookie_jar).await?;
+            let (mut write, mut read) = ws_stram.split();
+
+            let handshake_msg = add_suffix(json!({"protocol":"json","version":1}).to_string());
+            let echo_msg = add_suffix(json!({"type":6}).to_string());
+
+            write.send(Text(handshake_msg)).await?;
+            read.next().await;
+            write.send(Text(echo_msg)).await?;
+            Ok::<_, BingError>((write, read))
+        };
+        match self.config.chat_timeouts.handshake {
+            Some(timeout) => tokio::time::timeout(timeout, handshake)
+                .await
+                .map_err(|_| BingError::ChatTimeout {
+                    phase: TimeoutPhase::Handshake,
+                    partial_text: String::new(),
+                })?,
+            None => handshake.await,
+        }
+    }
+
+    // Reads the answer to `user_input` until the final frame, every response is sent to `tx`.
+    // When `tx` is closed the answer is stopped and the draw tasks are aborted.
+    // With `resendable`, a connection that ends before any frame is `Unanswered` instead of an error,
+    // e.g. a reused one bing closed while the question was sent.
+    pub(crate) async fn read_answer(
+        &self,
+        chat: &Chat,
+        (write, read): &mut Connection,
+        user_input: &UserInput,
+        signal: &mut StopSignal,
+        tx: mpsc::Sender<Result<BotResp, BingError>>,
+        resendable: bool,
+    ) -> AnswerEnd {
+        let echo_msg = add_suffix(json!({"type":6}).to_string());
+        // the stop has its own invocation id, its type 3 frame is the acknowledgement
+        let stop_id = user_input.stop_invocation_id();
+        let stop_msg = add_suffix(
+            json!({"arguments":[{}],"invocationId":stop_id,"target":"stop","type":1}).to_string(),
+        );
+        let mut draw_tasks = DrawTasks::default();
+        let mut text_tracker = TextTracker::default();
+        let mut stop_waiters: Vec<oneshot::Sender<StopOutcome>> = Vec::new();
+        let mut stop_deadline: Option<Instant> = None;
+        let mut stop_status = StopStatus::Closed;
+        let mut deadlines = Deadlines::new(self.config.chat_timeouts);
+        let mut timeout_phase = None;
+        // type 2 or 3 was received
+        let mut finished = false;
+        let mut answered = false;
+        let mut close_reason: Option<String> = None;
+        let mut final_error: Option<BingError> = None;
+        loop {
+            let next_deadline = deadlines.next();
+            let ws_msg_rst = tokio::select! {
+                biased;
+                _ = tx.closed() => {
+                    let _ = write.send(Text(stop_msg.clone())).await;
+                    let _ = write.close().await;
+                    return AnswerEnd::Closed;
+                }
+                waiter = signal.recv() => {
+                    if stop_deadline.is_none() {
+                        let _ = write.send(Text(stop_msg.clone())).await;
+                        stop_deadline = Some(Instant::now() + self.config.stop_timeout);
+                    }
+                    stop_waiters.extend(waiter);
+                    continue;
+                }
+                _ = sleep_until(stop_deadline.unwrap_or_else(Instant::now)), if stop_deadline.is_some() => {
+                    stop_status = StopStatus::TimedOut;
+                    break;
+                }
+                _ = sleep_until(next_deadline.map_or_else(Instant::now, |(at, _)| at)), if next_deadline.is_some() => {
+                    stop_status = StopStatus::TimedOut;
+                    timeout_phase = next_deadline.map(|(_, phase)| phase);
+                    break;
+                }
+                ws_msg_rst = read.next() => match ws_msg_rst {
+                    Some(ws_msg_rst) => ws_msg_rst,
+                    None => break,
+                },
+            };
+            if ws_msg_rst.is_ok() {
+                deadlines.last_frame = Instant::now();
+            }
+            let ws_msg = match ws_msg_rst {
+                Ok(ws_msg) => ws_msg,
+                Err(WsError::ConnectionClosed | WsError::AlreadyClosed) => break,
+                Err(e) => {
+                    final_error = Some(e.into());
+                    break;
+                }
+            };
+            let texts = match ws_msg {
+                Text(texts) => {
+                    answered = true;
+                    texts
+                }
+                Message::Close(frame) => {
+                    close_reason = frame
+                        .map(|frame| frame.reason.to_string())
+                        .filter(|reason| !reason.is_empty());
+                    break;
+                }
+                _ => continue,
+            };
+            let texts = texts
+                .trim_end_matches('\x1e')
+                .split('\x1e')
+                .filter(|s| !s.is_empty());
+            for text in texts {
+                let json = match serde_json::Value::from_str(text) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        let error = BingError::InvalidFrame {
+                            frame: text.to_string(),
+                            reason: e.to_string(),
+                        };
+                        if tx.send(Err(error)).await.is_err() {
+                            return AnswerEnd::Closed;
+                        }
+                        continue;
+                    }
+                };
+                match json["type"].as_u64() {
+                    Some(1) => {
+                        let (botresps, errors) = json2bot_resp_type1(&json, &mut draw_tasks.0, self);
+                        if !botresps.is_empty() {
+                            deadlines.responded = true;
+                        }
+                        let botresps = text_tracker.track(botresps, self.config.text_mode);
+                        signal.set_text(text_tracker.text());
+                        for botresp in botresps {
+                            if let BotResp::Disengaged(_) = botresp {
+                                chat.disengaged.store(true, Ordering::SeqCst);
+                            }
+                            if tx.send(Ok(botresp)).await.is_err() {
+                                return AnswerEnd::Closed;
+                            }
+                        }
+                        for error in errors {
+                            if tx.send(Err(error)).await.is_err() {
+                                return AnswerEnd::Closed;
+                            }
+                        }
+                    }
+                    // 如果正常回答完毕，则最后消息为2类型
+                    Some(2) => {
+                        let invalid = |reason| BingError::InvalidFrame {
+                            frame: text.to_string(),
+                            reason,
+                        };
+                        let botresps: Vec<_> = match json2bot_resp_type2(&json) {
+                            Ok((resps, skipped)) => {
+                                // a withdrawal may only show in the final frame
+                                let resps = text_tracker.track(resps, self.config.text_mode);
+                                let skipped = skipped.into_iter().map(|reason| Err(invalid(reason)));
+                                resps.into_iter().map(Ok).chain(skipped).collect()
+                            }
+                            Err(reason) => vec![Err(invalid(reason))],
+                        };
+                        for botresp in botresps {
+                            if tx.send(botresp).await.is_err() {
+                                return AnswerEnd::Closed;
+                            }
+                        }
+                        // eg: "Throttled", "CaptchaChallenge", "UnauthorizedRequest"
+                        let result = &json["item"]["result"];
+                        if let Some(value) = result["value"].as_str() {
+                            if value == "Success" {
+                                chat.clear_seed();
+                                let throttling = &json["item"]["throttling"];
+                                let turns = throttling["numUserMessagesInConversation"]
+                                    .as_u64()
+                                    .map_or(chat.session().turns + 1, |turns| turns as u32);
+                                chat.set_turns(turns);
+                                if let Some(max_turns) =
+                                    throttling["maxNumUserMessagesInConversation"].as_u64()
+                                {
+                                    chat.set_max_turns(max_turns as u32);
+                                }
+                            } else {
+                                final_error = Some(BingError::from_service(
+                                    ASK_ACTION,
+                                    value,
+                                    result["message"].as_str().map(str::to_string),
+                                ));
+                            }
+                        }
+                        stop_status = StopStatus::Finished;
+                        finished = true;
+                        break;
+                    }
+                    // 如果用户取消回答，则最后消息为3类型
+                    Some(3) => {
+                        stop_status = match json["invocationId"].as_str() {
+                            Some(id) if id == stop_id => StopStatus::Acknowledged,
+                            // the acknowledgement of a stop of an earlier question
+                            Some(id) if id != user_input.invocationId => continue,
+                            // the question itself is complete
+                            _ => StopStatus::Finished,
+                        };
+                        if let Some(error) = json["error"].as_str() {
+                            final_error = Some(BingError::from_service(ASK_ACTION, error, None));
+                        }
+                        finished = true;
+                        break;
+                    }
+                    // 6消息类型为心跳数据，我们直接跟随服务器端进行心跳即可
+                    Some(6) => {
+                        let _ = write.send(Text(echo_msg.clone())).await;
+                    }
+                    // the server closes the connection, eg: {"type":7,"error":"...","allowReconnect":true}
+                    Some(7) => {
+                        close_reason = json["error"].as_str().map(str::to_string);
+                        break;
+                    }
+                    Some(_) => {}
+                    None if json.as_object().is_some_and(|json| json.is_empty()) => {}
+                    None => {
+                        let error = BingError::InvalidFrame {
+                            frame: text.to_string(),
+                            reason: "No type in the frame".to_string(),
+                        };
+                        if tx.send(Err(error)).await.is_err() {
+                            return AnswerEnd::Closed;
+                        }
+                    }
+                }
+            }
+            if finished || close_reason.is_some() {
+                break;
+            }
+        }
+        // a close reason is bing's answer to the question
+        if resendable
+            && !answered
+            && close_reason.is_none()
+            && timeout_phase.is_none()
+            && stop_waiters.is_empty()
+            && !signal.check_stop()
+        {
+            return AnswerEnd::Unanswered;
+        }
+        for waiter in stop_waiters {
+            let _ = waiter.send(StopOutcome {
+                status: stop_status,
+                text: signal.text(),
+            });
+        }
+        // closed by the server before the answer finished
+        if !finished && timeout_phase.is_none() && final_error.is_none() && !signal.check_stop() {
+            final_error = Some(BingError::ChatClosed {
+                reason: close_reason,
+                partial_text: text_tracker.text().to_string(),
+            });
+        }
+        // the connection is stalled, so the draw tasks are dropped with it
+        if let Some(phase) = timeout_phase {
+            let _ = tx
+                .send(Err(BingError::ChatTimeout {
+                    phase,
+                    partial_text: text_tracker.text().to_string(),
+                }))
+                .await;
+            return AnswerEnd::Closed;
+        }
+        for task in draw_tasks.0.iter_mut() {
+            let error = match task.await {
+                Ok(Ok((resp, payload))) => {
+                    if tx.send(Ok(resp)).await.is_err() {
+                        return AnswerEnd::Closed;
+                    }
+                    match self.update_conversation(chat, payload).await {
+                        Ok(()) => continue,
+                        Err(e) => e,
+                    }
+                }
+                Ok(Err(e)) => e,
+                Err(e) => e.into(),
+            };
+            if tx.send(Err(BingError::AnswerImage(Box::new(error)))).await.is_err() {
+                return AnswerEnd::Closed;
+            }
+        }
+        // the next call fetches a new signature
+        if final_error
+            .as_ref()
+            .is_some_and(BingError::is_signature_rejected)
+        {
+            chat.set_signature_time(None);
+        }
+        // only a complete answer leaves nothing of it on the connection
+        let reusable = stop_status == StopStatus::Finished && final_error.is_none();
+        if let Some(e) = final_error {
+            let _ = tx.send(Err(e)).await;
+        }
+        if reusable {
+            AnswerEnd::Reusable
+        } else {
+            AnswerEnd::Closed
+        }
+    }
+}
+
+// When an answer times out, see `ChatTimeouts`
+struct Deadlines {
+    timeouts: ChatTimeouts,
+    started: Instant,
+    last_frame: Instant,
+    // a type 1 frame with a response was received
+    responded: bool,
+}
+
+impl Deadlines {
+    fn new(timeouts: ChatTimeouts) -> Self {
+        let now = Instant::now();
+        Deadlines {
+            timeouts,
+            started: now,
+            last_frame: now,
+            responded: false,
+        }
+    }
+
+    // The earliest deadline
+    fn next(&self) -> Option<(Instant, TimeoutPhase)> {
+        let first_token = match self.responded {
+            false => self.timeouts.first_token,
+            true => None,
+        };
+        [
+            first_token.map(|timeout| (self.started + timeout, TimeoutPhase::FirstToken)),
+            self.timeouts
+                .idle
+                .map(|timeout| (self.last_frame + timeout, TimeoutPhase::Idle)),
+            self.timeouts
+                .total
+                .map(|timeout| (self.started + timeout, TimeoutPhase::Total)),
+        ]
+        .into_iter()
+        .flatten()
+        .min_by_key(|(at, _)| *at)
+    }
+}
+
+// Draw tasks of an answer, the unfinished ones are aborted when the answer is dropped
+#[derive(Default)]
+struct DrawTasks(Vec<DrawTask>);
+
+impl Drop for DrawTasks {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
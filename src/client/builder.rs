@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use crate::types::{
+    chat_timeout_type::ChatTimeouts,
+    client_config_type::{ClientConfig, ProxyAuth, RolloverPolicy, TextMode, TlsMode},
+    cookie_report_type::CookieCheck,
+    cookie_type::Cookie,
+    error_type::BingError,
+    locale_type::Locale,
+};
+
+use super::BingClient;
+
+#[derive(Debug, Clone, Default)]
+pub struct BingClientBuilder {
+    config: ClientConfig,
+}
+
+fn trim_host(host: String) -> String {
+    host.trim_end_matches('/').to_string()
+}
+
+impl BingClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // eg: "https://www.bing.com"
+    pub fn www_host(mut self, host: impl Into<String>) -> Self {
+        self.config.endpoints.www = trim_host(host.into());
+        self
+    }
+
+    // eg: "https://sydney.bing.com"
+    pub fn sydney_host(mut self, host: impl Into<String>) -> Self {
+        self.config.endpoints.sydney = trim_host(host.into());
+        self
+    }
+
+    // eg: "wss://sydney.bing.com"
+    pub fn wss_host(mut self, host: impl Into<String>) -> Self {
+        self.config.endpoints.wss = trim_host(host.into());
+        self
+    }
+
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.config.request_timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn chat_timeouts(mut self, chat_timeouts: ChatTimeouts) -> Self {
+        self.config.chat_timeouts = chat_timeouts;
+        self
+    }
+
+    // How long `StopHandle::stop` waits for bing to confirm, 5 seconds by default
+    pub fn stop_timeout(mut self, timeout: Duration) -> Self {
+        self.config.stop_timeout = timeout;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.config.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn text_mode(mut self, text_mode: TextMode) -> Self {
+        self.config.text_mode = text_mode;
+        self
+    }
+
+    pub fn heartbeat_interval(mut self, heartbeat_interval: Duration) -> Self {
+        self.config.heartbeat_interval = heartbeat_interval;
+        self
+    }
+
+    pub fn signature_ttl(mut self, signature_ttl: Duration) -> Self {
+        self.config.signature_ttl = signature_ttl;
+        self
+    }
+
+    pub fn image_concurrency(mut self, image_concurrency: usize) -> Self {
+        self.config.image_concurrency = image_concurrency;
+        self
+    }
+
+    pub fn rollover(mut self, rollover: RolloverPolicy) -> Self {
+        self.config.rollover = rollover;
+        self
+    }
+
+    pub fn tls_mode(mut self, tls_mode: TlsMode) -> Self {
+        self.config.tls_mode = tls_mode;
+        self
+    }
+
+    pub fn accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.config.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    // Proxy for every request and the ChatHub websocket, eg: "socks5h://127.0.0.1:1080"
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.config.proxy.all = Some(url.into());
+        self
+    }
+
+    pub fn http_proxy(mut self, url: impl Into<String>) -> Self {
+        self.config.proxy.http = Some(url.into());
+        self
+    }
+
+    pub fn https_proxy(mut self, url: impl Into<String>) -> Self {
+        self.config.proxy.https = Some(url.into());
+        self
+    }
+
+    pub fn proxy_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.config.proxy.auth = Some(ProxyAuth {
+            username: username.into(),
+            password: password.into(),
+        });
+        self
+    }
+
+    // Locale, market, region and location of chat requests, also sets the `_EDGE_CD` cookie
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.config.locale = locale;
+        self
+    }
+
+    pub fn cookie_check(mut self, cookie_check: CookieCheck) -> Self {
+        self.config.cookie_check = cookie_check;
+        self
+    }
+
+    // Replaces the default `["_U"]`
+    pub fn required_cookies<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.required_cookies = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub async fn build(self, cookie: &Cookie) -> Result<BingClient, BingError> {
+        let mut client = BingClient::init(cookie, self.config).await?;
+        client.update_client_id().await?;
+        Ok(client)
+    }
+
+    pub async fn build_with_chats(self, cookie: &Cookie) -> Result<BingClient, BingError> {
+        let mut client = BingClient::init(cookie, self.config).await?;
+        client.update_chats_client_id().await?;
+        Ok(client)
+    }
+}
@@ -0,0 +1,250 @@
+use std::sync::{Arc, Mutex};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::{interval, MissedTickBehavior},
+};
+use tokio_tungstenite::tungstenite::{
+    Error as WsError,
+    Message::{self, Text},
+};
+
+use crate::{
+    types::{
+        bot_answer_type::BotAnswer, bot_easy_resp_type::BotResp, chat_type::Chat,
+        error_type::BingError, locale_type::Locale, user_input_type::UserInput,
+    },
+    utils::{msg_proces::add_suffix, stop_signal::StopSignal},
+};
+
+use super::{
+    ask_stream::{AnswerEnd, AskStream, Connection, CHANNEL_SIZE},
+    BingClient,
+};
+
+// A question waiting for the connection
+struct Turn {
+    user_input: UserInput,
+    signal: StopSignal,
+    tx: mpsc::Sender<Result<BotResp, BingError>>,
+    // whether the question was sent
+    sent: oneshot::Sender<Result<(), BingError>>,
+}
+
+// One ChatHub connection for all the questions of a chat.
+// A background task keeps it alive between answers and reconnects when bing closes it,
+// questions are answered one after another. Dropping the session closes the connection.
+// At the message limit the session moves to a new chat, see `RolloverPolicy`.
+pub struct ChatSession {
+    chat: Arc<Mutex<Arc<Chat>>>,
+    turns: mpsc::Sender<Turn>,
+}
+
+impl ChatSession {
+    // Connects right away, so the first question does not wait for the handshake
+    pub async fn new(client: Arc<BingClient>, chat: Arc<Chat>) -> Result<ChatSession, BingError> {
+        let connection = client.handshake_chat_hub(&chat, None).await?;
+        let (turns, turns_rx) = mpsc::channel(1);
+        let chat = Arc::new(Mutex::new(chat));
+        tokio::spawn(drive(client, chat.clone(), Some(connection), turns_rx));
+        Ok(ChatSession { chat, turns })
+    }
+
+    // The chat of the next question, a new one after a rollover
+    pub fn chat(&self) -> Arc<Chat> {
+        self.chat.lock().unwrap().clone()
+    }
+
+    // Waits until the previous answer is complete, then sends the question
+    pub async fn ask_stream(&self, user_input: UserInput) -> Result<AskStream, BingError> {
+        let signal = StopSignal::new();
+        let stop_handle = signal.handle();
+        let (tx, rx) = mpsc::channel(CHANNEL_SIZE);
+        let (sent, sent_rx) = oneshot::channel();
+        let turn = Turn {
+            user_input,
+            signal,
+            tx,
+            sent,
+        };
+        let closed = || BingError::ChatClosed {
+            reason: Some("The chat session has ended".to_string()),
+            partial_text: String::new(),
+        };
+        self.turns.send(turn).await.map_err(|_| closed())?;
+        sent_rx.await.map_err(|_| closed())??;
+        Ok(AskStream::from_channel(rx, stop_handle))
+    }
+
+    // Like `BingClient::ask`, on the session's connection
+    pub async fn ask(&self, user_input: UserInput) -> Result<BotAnswer, BingError> {
+        self.ask_stream(user_input).await?.answer().await
+    }
+}
+
+// Owns the connection: answers the turns, sends heartbeats in between and
+// drops the connection when bing closes it, the next turn reconnects.
+// Bing may close it just as a question is sent, then the question is sent again on a new one.
+async fn drive(
+    client: Arc<BingClient>,
+    session_chat: Arc<Mutex<Arc<Chat>>>,
+    mut connection: Option<Connection>,
+    mut turns: mpsc::Receiver<Turn>,
+) {
+    let echo_msg = add_suffix(json!({"type":6}).to_string());
+    let mut heartbeat = interval(client.config.heartbeat_interval);
+    heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    heartbeat.reset();
+    let mut invocation_id: u64 = 0;
+    // the locale the connection was opened with, see `UserInput::with_locale`
+    let mut connection_locale: Option<Locale> = None;
+    let mut chat = session_chat.lock().unwrap().clone();
+    loop {
+        tokio::select! {
+            biased;
+            frame = next_frame(&mut connection) => match frame {
+                Some(Ok(Text(texts))) => {
+                    let types = frame_types(&texts);
+                    // the server closes the connection, see `read_answer`
+                    if types.contains(&7) {
+                        if let Some((mut write, _)) = connection.take() {
+                            let _ = write.close().await;
+                        }
+                    // pings of the server are answered like during an answer
+                    } else if types.contains(&6) {
+                        if let Some((write, _)) = &mut connection {
+                            if write.send(Text(echo_msg.clone())).await.is_err() {
+                                connection = None;
+                            }
+                        }
+                    }
+                }
+                Some(Ok(Message::Ping(_) | Message::Pong(_))) => {}
+                // a close frame, the end of the stream or a reset
+                _ => connection = None,
+            },
+            turn = turns.recv() => {
+                let Some(mut turn) = turn else {
+                    break;
+                };
+                let rollover = match client.rollover_question(&chat, &mut turn.user_input).await {
+                    Ok(rollover) => rollover,
+                    Err(e) => {
+                        let _ = turn.sent.send(Err(e));
+                        continue;
+                    }
+                };
+                // the connection belongs to the old chat
+                if let Some(rollover) = rollover {
+                    chat = rollover.chat.clone();
+                    *session_chat.lock().unwrap() = chat.clone();
+                    if let Some((mut write, _)) = connection.take() {
+                        let _ = write.close().await;
+                    }
+                    let _ = turn.tx.send(Ok(BotResp::Rollover(rollover))).await;
+                }
+                // the id after it is the one of its stop
+                turn.user_input.invocationId = invocation_id.to_string();
+                invocation_id += 2;
+                // the `_EDGE_CD` cookie is only sent with the handshake
+                if connection_locale != turn.user_input.locale {
+                    if let Some((mut write, _)) = connection.take() {
+                        let _ = write.close().await;
+                    }
+                }
+                let mut sent = Some(turn.sent);
+                loop {
+                    let (mut answering, reused) =
+                        match send_turn(&client, &chat, connection.take(), &turn.user_input).await {
+                            Ok(sent) => sent,
+                            Err(e) => {
+                                match sent.take() {
+                                    Some(sent) => {
+                                        let _ = sent.send(Err(e));
+                                    }
+                                    None => {
+                                        let _ = turn.tx.send(Err(e)).await;
+                                    }
+                                }
+                                break;
+                            }
+                        };
+                    connection_locale = turn.user_input.locale.clone();
+                    if let Some(sent) = sent.take() {
+                        let _ = sent.send(Ok(()));
+                    }
+                    let end = client
+                        .read_answer(
+                            &chat,
+                            &mut answering,
+                            &turn.user_input,
+                            &mut turn.signal,
+                            turn.tx.clone(),
+                            reused,
+                        )
+                        .await;
+                    match end {
+                        AnswerEnd::Reusable => connection = Some(answering),
+                        AnswerEnd::Closed => {
+                            let _ = answering.0.close().await;
+                        }
+                        // a new connection is not reused, so this is sent again only once
+                        AnswerEnd::Unanswered => continue,
+                    }
+                    break;
+                }
+                heartbeat.reset();
+            }
+            _ = heartbeat.tick() => {
+                if let Some((write, _)) = &mut connection {
+                    if write.send(Text(echo_msg.clone())).await.is_err() {
+                        connection = None;
+                    }
+                }
+            }
+        }
+    }
+    if let Some((mut write, _)) = connection {
+        let _ = write.close().await;
+    }
+}
+
+// Never resolves without a connection
+async fn next_frame(connection: &mut Option<Connection>) -> Option<Result<Message, WsError>> {
+    match connection {
+        Some((_, read)) => read.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+// The `type` of every record of a text frame
+fn frame_types(texts: &str) -> Vec<u64> {
+    texts
+        .split('\x1e')
+        .filter_map(|text| serde_json::from_str::<serde_json::Value>(text).ok())
+        .filter_map(|json| json["type"].as_u64())
+        .collect()
+}
+
+// Sends the question on `connection`, on a new connection if there is none or it is broken.
+// Returns whether `connection` was used, a write can succeed on a connection bing already closed.
+async fn send_turn(
+    client: &BingClient,
+    chat: &Chat,
+    connection: Option<Connection>,
+    user_input: &UserInput,
+) -> Result<(Connection, bool), BingError> {
+    let message = Text(serde_json::to_string(user_input)?);
+    if let Some((mut write, read)) = connection {
+        if write.send(message.clone()).await.is_ok() {
+            return Ok(((write, read), true));
+        }
+    }
+    let (mut write, read) = client
+        .handshake_chat_hub(chat, user_input.locale.as_ref())
+        .await?;
+    write.send(message).await?;
+    Ok(((write, read), false))
+}
@@ -1,46 +1,52 @@
-use std::{future::Future, str::FromStr};
+pub mod ask_stream;
+pub mod builder;
+pub mod chat_session;
+
+use std::{future::Future, sync::Arc};
 
-use futures_util::{future::join_all, SinkExt, StreamExt};
 use genawaiter::{sync::Gen, GeneratorState};
-use http::HeaderValue;
-use reqwest::{header::HeaderMap, multipart, Client as ReqwestClient, StatusCode};
+use reqwest::{header::HeaderMap, multipart, Client as ReqwestClient};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use tokio::{sync::RwLock, task::JoinError};
-use tokio_tungstenite::{
-    connect_async,
-    tungstenite::{client::IntoClientRequest, Message::Text},
-};
+use tokio::sync::{mpsc, RwLock};
 use uuid::Uuid;
 
 use crate::{
     const_vars::{
-        gen_chat_hub_wss_url, gen_get_chat_messages_url, gen_get_chat_signature_url,
-        gen_image_payload_url, CREATE_CHAT_URL, DELETE_CHATS_URL, DELETE_CHAT_URL,
-        GEN_IMAGE_ID_URL, GET_CHAT_ID_URL, GET_CHAT_LIST_URL, RENAME_CHAT_URL,
-        UPDATE_CONVERSATION_URL,
+        gen_create_chat_url, gen_delete_chat_url, gen_delete_chats_url, gen_get_chat_id_url,
+        gen_get_chat_list_url, gen_get_chat_messages_url, gen_get_chat_signature_url,
+        gen_image_id_url, gen_image_payload_url, gen_referer_url, gen_rename_chat_url,
+        gen_update_conversation_url,
     },
     types::{
-        bot_easy_resp_type::BotResp,
+        bot_answer_type::BotAnswer,
+        bot_easy_resp_type::{AnswerError, BotResp},
         chat_msg_type::EasyMsg,
         chat_type::{Chat, ChatListResp},
+        conversation_type::Conversation,
+        client_config_type::{ClientConfig, RolloverPolicy, TextMode},
         client_info_type::GetClientInfoResponse,
+        cookie_report_type::{CookieCheck, CookieReport, ExpiredCookie},
         cookie_type::Cookie,
         create_chat_type::CreateChatChatResp,
         delete_chat_type::{
             DeleteChatPayload, DeleteChatResp, DeleteChatsPayload, DeleteChatsResp, TodelChats,
         },
+        error_type::BingError,
         rename_chat_type::{RenameChatRequest, RenameChatResp},
+        rollover_type::ChatRollover,
         update_conversation::UpdateConversaionResp,
         user_input_type::UserInput,
     },
     utils::{
-        cookie_pre::parse_cookie,
+        answer_render::{AnswerRenderer, MarkdownRenderer},
+        cookie_import::to_netscape,
+        cookie_jar::{CookieJar, JsonCookie},
+        cookie_pre::load_cookie_jar,
         draw_image::{gen_pool_image_url, poll_images},
+        http_client::{build_reqwest_client, check_status, json_resp},
         image_base64::Image,
-        msg_proces::add_suffix,
-        process_bot_resp::{json2bot_resp_type1, json2bot_resp_type2},
-        process_chat_msgs::process_chat_msgs,
+        process_chat_msgs::{process_chat_msgs, resolve_images},
         stop_signal::StopSignal,
     },
 };
@@ -48,110 +54,242 @@ use crate::{
 use serde::de::{self, MapAccess, Visitor};
 use serde::Deserializer;
 
+use self::{
+    ask_stream::{AskStream, CHANNEL_SIZE},
+    builder::BingClientBuilder,
+};
+
 #[derive(Debug, Serialize)]
 pub struct BingClient {
     #[serde(skip)]
     pub reqwest_client: ReqwestClient,
-    pub cookie_str: String,
+    // Does not follow redirects, used for image drawing and downloading.
+    // It shares the cookie jar, which sends a cookie only to the hosts of its domain,
+    // eg: the bing cookies only to *.bing.com
+    #[serde(skip)]
+    pub(crate) no_redirect_client: ReqwestClient,
+    // updated by the `Set-Cookie` headers of every response, shared by both clients
+    #[serde(rename = "cookies")]
+    pub cookie_jar: Arc<CookieJar>,
+    // `save_cookies` writes the cookies back here, only set for `JsonPath`, `HeadPath` and `NetscapePath`
+    pub cookie_source: Option<Cookie>,
+    // Problems found in the cookies when the client was built
+    #[serde(skip)]
+    pub cookie_report: CookieReport,
     pub client_id: String,
     pub chats: Vec<Chat>,
+    pub config: ClientConfig,
 }
 
 impl BingClient {
-    pub(crate) fn gen_header(&self) -> Result<HeaderMap, anyhow::Error> {
-        let mut headers = HeaderMap::new();
-        headers.insert(reqwest::header::COOKIE, self.cookie_str.parse()?);
-        headers.insert("Referer", "https://www.bing.com/search?q=Bing+Ai".parse()?);
-        Ok(headers)
+    pub(crate) fn gen_header(&self) -> Result<HeaderMap, BingError> {
+        gen_referer_header(&self.config)
     }
 
-    async fn init(cookie: &Cookie) -> Result<BingClient, anyhow::Error> {
-        let cookie_string = parse_cookie(cookie).await?;
-        let mut headers = HeaderMap::new();
-        headers.insert(reqwest::header::COOKIE, cookie_string.parse()?);
-        headers.insert("Referer", "https://www.bing.com/search?q=Bing+Ai".parse()?);
-        #[cfg(feature = "allow-invalid-tls")]
-        {
-            Ok(BingClient {
-                reqwest_client: {
-                    ReqwestClient::builder()
-                        .default_headers(headers)
-                        .danger_accept_invalid_certs(true)
-                        .build()?
-                },
-                cookie_str: cookie_string,
-                client_id: String::new(),
-                chats: Vec::new(),
-            })
+    async fn init(cookie: &Cookie, config: ClientConfig) -> Result<BingClient, BingError> {
+        config.locale.validate()?;
+        let (cookie_jar, cookie_report) = load_cookie_jar(cookie, &config).await?;
+        let cookie_source = match cookie {
+            Cookie::JsonPath(_) | Cookie::HeadPath(_) | Cookie::NetscapePath(_) => {
+                Some(cookie.clone())
+            }
+            _ => None,
+        };
+        let mut client =
+            BingClient::from_parts(cookie_jar, cookie_source, String::new(), Vec::new(), config)?;
+        client.cookie_report = cookie_report;
+        Ok(client)
+    }
+
+    fn from_parts(
+        cookie_jar: CookieJar,
+        cookie_source: Option<Cookie>,
+        client_id: String,
+        chats: Vec<Chat>,
+        config: ClientConfig,
+    ) -> Result<BingClient, BingError> {
+        let cookie_jar = Arc::new(cookie_jar);
+        let headers = gen_referer_header(&config)?;
+        Ok(BingClient {
+            reqwest_client: build_reqwest_client(&config, headers, true, Some(cookie_jar.clone()))?,
+            no_redirect_client: build_reqwest_client(
+                &config,
+                HeaderMap::new(),
+                false,
+                Some(cookie_jar.clone()),
+            )?,
+            cookie_jar,
+            cookie_source,
+            cookie_report: CookieReport::default(),
+            client_id,
+            chats,
+            config,
+        })
+    }
+
+    // `cookie_report` brought up to date: the cookies that expired when the client was built
+    // or since, the required ones that are missing now and the foreign ones.
+    // Nothing is checked with `CookieCheck::Off`.
+    pub fn check_cookies(&self) -> CookieReport {
+        let mut report = self.cookie_report.clone();
+        if self.config.cookie_check == CookieCheck::Off {
+            return report;
         }
-        #[cfg(not(feature = "allow-invalid-tls"))]
-        {
-            Ok(BingClient {
-                reqwest_client: { ReqwestClient::builder().default_headers(headers).build()? },
-                cookie_str: cookie_string,
-                client_id: String::new(),
-                chats: Vec::new(),
+        for cookie in self.cookie_jar.expired_cookies() {
+            if !report
+                .expired
+                .iter()
+                .any(|expired| expired.name == cookie.name && expired.domain == cookie.domain)
+            {
+                report.expired.push(ExpiredCookie {
+                    required: self.config.required_cookies.contains(&cookie.name),
+                    ..cookie
+                });
+            }
+        }
+        report.missing = self
+            .config
+            .required_cookies
+            .iter()
+            .filter(|name| {
+                !self.cookie_jar.contains(name)
+                    && !report.expired.iter().any(|cookie| &&cookie.name == name)
             })
+            .cloned()
+            .collect();
+        report
+    }
+
+    // The current `Cookie` header sent to `endpoints.www`
+    pub fn cookie_str(&self) -> String {
+        match reqwest::Url::parse(&self.config.endpoints.www) {
+            Ok(url) => self.cookie_jar.cookie_header(&url),
+            Err(_) => String::new(),
         }
     }
 
-    async fn update_chat_signature(&self, chat: &Chat) -> Result<(), anyhow::Error> {
+    // Writes the current cookies back to the `JsonPath`, `HeadPath` or `NetscapePath` they were loaded from
+    pub async fn save_cookies(&self) -> Result<(), BingError> {
+        match &self.cookie_source {
+            Some(cookie_source) => self.save_cookies_to(cookie_source).await,
+            None => Err(BingError::Cookie(
+                "The cookies were not loaded from a file, use `save_cookies_to` instead."
+                    .to_string(),
+            )),
+        }
+    }
+
+    pub async fn save_cookies_to(&self, target: &Cookie) -> Result<(), BingError> {
+        match target {
+            Cookie::JsonPath(path) => {
+                let json = serde_json::to_string_pretty(&self.cookie_jar.to_json_cookies())?;
+                tokio::fs::write(path, json).await?;
+            }
+            Cookie::HeadPath(path) => tokio::fs::write(path, self.cookie_str()).await?,
+            Cookie::NetscapePath(path) => {
+                tokio::fs::write(path, to_netscape(&self.cookie_jar.to_json_cookies())).await?
+            }
+            _ => {
+                return Err(BingError::Cookie(
+                    "Cookies can only be saved to a JsonPath, HeadPath or NetscapePath."
+                        .to_string(),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    async fn update_chat_signature(&self, chat: &Chat) -> Result<(), BingError> {
+        const ACTION: &str = "Get Bing Copilot Chat Signature";
         let resp = self
             .reqwest_client
-            .get(gen_get_chat_signature_url(&chat.conversation_id))
+            .get(gen_get_chat_signature_url(
+                &self.config.endpoints,
+                &chat.conversation_id,
+            ))
             .send()
             .await?;
-        match resp.status() {
-            StatusCode::OK => {
-                if let Some(x_sydney_conversationsignature) =
-                    resp.headers().get("X-Sydney-Conversationsignature")
-                {
-                    {*chat.x_sydney_conversationsignature.write().await =
-                        Some(x_sydney_conversationsignature.to_str()?.to_string()).into();}
-                } else {
-                    return Err(anyhow::anyhow!(
-                        "Get Bing Copilot Chat X-Sydney-Conversationsignature Failed; No X-Sydney-Conversationsignature in resp headers.",
-                    ))
-                };
+        let resp = check_status(ACTION, resp).await?;
+        let get_header = |header: &'static str| -> Result<String, BingError> {
+            Ok(resp
+                .headers()
+                .get(header)
+                .ok_or(BingError::MissingHeader {
+                    action: ACTION,
+                    header,
+                })?
+                .to_str()?
+                .to_string())
+        };
+        let x_sydney_conversationsignature = get_header("X-Sydney-Conversationsignature")?;
+        let x_sydney_encryptedconversationsignature =
+            get_header("X-Sydney-Encryptedconversationsignature")?;
+        *chat.x_sydney_conversationsignature.write().await = Some(x_sydney_conversationsignature);
+        *chat.x_sydney_encryptedconversationsignature.write().await =
+            Some(x_sydney_encryptedconversationsignature);
+        chat.set_signature_time(Some(std::time::Instant::now()));
+        Ok(())
+    }
 
-                if let Some(x_sydney_encryptedconversationsignature) =
-                resp.headers().get("X-Sydney-Encryptedconversationsignature")
-                {
-                    {*chat.x_sydney_encryptedconversationsignature.write().await =
-                        Some(x_sydney_encryptedconversationsignature.to_str()?.to_string()).into();}
-                    Ok(())
-                } else {
-                    Err(anyhow::anyhow!(
-                        "Get Bing Copilot Chat X-Sydney-Encryptedconversationsignature Failed; No X-Sydney-Encryptedconversationsignature in resp headers.",
-                    ))
-                }
+    // Fetches the signatures of `chat` unless they are cached and fresh
+    pub(crate) async fn ensure_chat_signature(&self, chat: &Chat) -> Result<(), BingError> {
+        let cached = chat.x_sydney_conversationsignature.read().await.is_some()
+            && chat.x_sydney_encryptedconversationsignature.read().await.is_some();
+        if cached && chat.has_fresh_signature(self.config.signature_ttl) {
+            return Ok(());
+        }
+        self.update_chat_signature(chat).await
+    }
+
+    // Sends `request` with the cached signature of `chat`,
+    // if bing rejects it the signature is fetched again and `request` is sent once more
+    async fn with_chat_signature<T, F, Fut>(&self, chat: &Chat, request: F) -> Result<T, BingError>
+    where
+        F: Fn(HeaderMap) -> Fut,
+        Fut: Future<Output = Result<T, BingError>>,
+    {
+        self.ensure_chat_signature(chat).await?;
+        match request(self.chat_headers(chat).await?).await {
+            Err(e) if e.is_signature_rejected() => {
+                self.update_chat_signature(chat).await?;
+                request(self.chat_headers(chat).await?).await
             }
-            _ => Err(anyhow::anyhow!(
-                "Get Bing Copilot Chat X-Sydney-Encryptedconversationsignature Failed; StatusCode:{}; Body:{}",
-                resp.status().as_str(),
-                resp.text().await?
-            )),
+            rst => rst,
         }
     }
 
-    pub async fn build(cookie: &Cookie) -> Result<BingClient, anyhow::Error> {
-        let mut client = Self::init(cookie).await?;
-        client.update_client_id().await?;
-        Ok(client)
+    async fn chat_headers(&self, chat: &Chat) -> Result<HeaderMap, BingError> {
+        let mut headers = self.gen_header()?;
+        let signature = chat.x_sydney_conversationsignature.read().await.clone();
+        headers.insert(
+            "Authorization",
+            reqwest::header::HeaderValue::from_str(&format!(
+                "Bearer {}",
+                signature.unwrap_or_default()
+            ))?,
+        );
+        Ok(headers)
     }
 
-    pub async fn build_with_chats(cookie: &Cookie) -> Result<BingClient, anyhow::Error> {
-        let mut client = Self::init(cookie).await?;
-        client.update_chats_client_id().await?;
-        Ok(client)
+    pub fn builder() -> BingClientBuilder {
+        BingClientBuilder::new()
+    }
+
+    pub async fn build(cookie: &Cookie) -> Result<BingClient, BingError> {
+        BingClientBuilder::new().build(cookie).await
+    }
+
+    pub async fn build_with_chats(cookie: &Cookie) -> Result<BingClient, BingError> {
+        BingClientBuilder::new().build_with_chats(cookie).await
     }
 
     pub(crate) async fn gen_upload_image_url(
         &self,
         image: Image,
         chat: &Chat,
-    ) -> Result<String, anyhow::Error> {
-        let image = image.to_base64().await?;
+    ) -> Result<String, BingError> {
+        // `Image::Url` can point anywhere, the jar sends the bing cookies only to bing hosts
+        let image = image.to_base64(&self.no_redirect_client).await?;
         let form = multipart::Form::new()
             .text(
                 "knowledgeRequest",
@@ -172,176 +310,146 @@ impl BingClient {
                 .to_string(),
             )
             .text("imageBase64", image);
+        const ACTION: &str = "Upload Bing Copilot Image";
         let resp = self
             .reqwest_client
-            .post(GEN_IMAGE_ID_URL)
+            .post(gen_image_id_url(&self.config.endpoints))
             .multipart(form)
             .send()
             .await?;
-        let status_code = resp.status().to_string();
-        match resp.status() {
-            StatusCode::OK => {
-                let resp_json: Value = resp.json().await?;
-                match &resp_json["blobId"] {
-                    Value::String(image_id) => Ok(gen_image_payload_url(&image_id)),
-                    _ => {
-                        Err(anyhow::anyhow!(
-                            "Upload Bing Copilot Image Failed;Status Code: {}; Error Message: No image_id found;",
-                            status_code,
-                        ))
-                    }
-                }
-            }
-            _ => Err(anyhow::anyhow!(
-                "Upload Bing Copilot Image Failed;Status Code: {}; Error Message: {:?}",
-                status_code,
-                resp.text().await?
-            )),
+        let resp_json: Value = json_resp(ACTION, resp).await?;
+        match &resp_json["blobId"] {
+            Value::String(image_id) => Ok(gen_image_payload_url(&self.config.endpoints, image_id)),
+            _ => Err(BingError::Service {
+                action: ACTION,
+                value: resp_json["result"]["value"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                message: Some("No image_id found".to_string()),
+            }),
         }
     }
 
-    pub async fn update_client_id(&mut self) -> Result<(), anyhow::Error> {
+    pub async fn update_client_id(&mut self) -> Result<(), BingError> {
         // this fn changes self.chat and self.client_id
-        let resp: GetClientInfoResponse = self
-            .reqwest_client
-            .get(GET_CHAT_ID_URL)
-            .send()
-            .await?
-            .json()
-            .await?;
+        const ACTION: &str = "Get Bing Copilot Client Info";
+        let resp: GetClientInfoResponse = json_resp(
+            ACTION,
+            self.reqwest_client
+                .get(gen_get_chat_id_url(&self.config.endpoints))
+                .send()
+                .await?,
+        )
+        .await?;
         if resp.result.value == "Success" {
             self.client_id = resp.client_id;
             Ok(())
         } else {
-            Err(anyhow::anyhow!(
-                "Get Bing Copilot Client Info Failed; Error Value {}; Error Message {:?}",
-                resp.result.value,
-                resp.result.message
-            ))
+            Err(BingError::from_result(ACTION, &resp.result))
         }
     }
 
-    pub async fn update_chats_client_id(&mut self) -> Result<(), anyhow::Error> {
+    pub async fn update_chats_client_id(&mut self) -> Result<(), BingError> {
         // this fn changes self.chat and self.client_id
-        let resp: ChatListResp = self
-            .reqwest_client
-            .get(GET_CHAT_LIST_URL)
-            .send()
-            .await?
-            .json()
-            .await?;
+        let resp = self.fetch_chat_list().await?;
+        self.client_id = resp.client_id;
+        self.chats = resp.chats;
+        Ok(())
+    }
+
+    async fn fetch_chat_list(&self) -> Result<ChatListResp, BingError> {
+        const ACTION: &str = "Get Bing Copilot Chat List";
+        let resp: ChatListResp = json_resp(
+            ACTION,
+            self.reqwest_client
+                .get(gen_get_chat_list_url(&self.config.endpoints))
+                .send()
+                .await?,
+        )
+        .await?;
         if resp.result.value == "Success" {
-            self.client_id = resp.client_id;
-            self.chats = resp.chats;
-            Ok(())
+            Ok(resp)
         } else {
-            Err(anyhow::anyhow!(
-                "Get Bing Copilot Chat List Failed; Error Value {}; Error Message {}",
-                resp.result.value,
-                resp.result.message
+            Err(BingError::from_service(
+                ACTION,
+                &resp.result.value,
+                Some(resp.result.message),
             ))
         }
     }
 
-    pub async fn get_chat_list(&self) -> Result<Vec<Chat>, anyhow::Error> {
-        let resp: ChatListResp = self
+    pub async fn get_chat_list(&self) -> Result<Vec<Chat>, BingError> {
+        Ok(self.fetch_chat_list().await?.chats)
+    }
+
+    pub async fn create_chat(&self) -> Result<Chat, BingError> {
+        const ACTION: &str = "Create Bing Copilot Chat";
+        let resp = self
             .reqwest_client
-            .get(GET_CHAT_LIST_URL)
+            .get(gen_create_chat_url(&self.config.endpoints))
             .send()
-            .await?
-            .json()
             .await?;
-        if resp.result.value == "Success" {
-            Ok(resp.chats)
-        } else {
-            Err(anyhow::anyhow!(
-                "Get Bing Copilot Chat List Failed; Error Value {}; Error Message {}",
-                resp.result.value,
-                resp.result.message
-            ))
-        }
-    }
-
-    pub async fn create_chat(&self) -> Result<Chat, anyhow::Error> {
-        let resp = self.reqwest_client.get(CREATE_CHAT_URL).send().await?;
-        match resp.status() {
-            StatusCode::OK => {
-                let x_sydney_conversationsignature = {
-                    if let Some(x_sydney_conversationsignature) =
-                    resp.headers().get("X-Sydney-Conversationsignature").cloned(){
-                        Some(x_sydney_conversationsignature.to_str()?.to_string())
-                    }else {
-                        None
-                    }
-                };
-                let x_sydney_encryptedconversationsignature = {
-                    if let Some(x_sydney_encryptedconversationsignature) =
-                    resp.headers().get("X-Sydney-Encryptedconversationsignature").cloned(){
-                        Some(x_sydney_encryptedconversationsignature.to_str()?.to_string())
-                    }else {
-                        None
-                    }
-                };
-                let resp_: CreateChatChatResp = resp.json().await?;
-                let new_chat = Chat{
-                    conversation_id: resp_.conversation_id,
-                    chat_name: None,
-                    conversation_signature: None,
-                    tone: None,
-                    create_time_utc: None,
-                    update_time_utc:None,
-                    plugins: Vec::new(),
-                    x_sydney_conversationsignature:RwLock::new(x_sydney_conversationsignature),
-                    x_sydney_encryptedconversationsignature:RwLock::new(x_sydney_encryptedconversationsignature), };
-                Ok(new_chat)
+        let resp = check_status(ACTION, resp).await?;
+        let x_sydney_conversationsignature = {
+            if let Some(x_sydney_conversationsignature) =
+            resp.headers().get("X-Sydney-Conversationsignature").cloned(){
+                Some(x_sydney_conversationsignature.to_str()?.to_string())
+            }else {
+                None
             }
-            _ => Err(anyhow::anyhow!(
-                "Get Bing Copilot Chat X-Sydney-Encryptedconversationsignature Failed; StatusCode:{}; Body:{}",
-                resp.status().as_str(),
-                resp.text().await?
-            )),
+        };
+        let x_sydney_encryptedconversationsignature = {
+            if let Some(x_sydney_encryptedconversationsignature) =
+            resp.headers().get("X-Sydney-Encryptedconversationsignature").cloned(){
+                Some(x_sydney_encryptedconversationsignature.to_str()?.to_string())
+            }else {
+                None
+            }
+        };
+        let resp_: CreateChatChatResp = json_resp(ACTION, resp).await?;
+        if resp_.result.value != "Success" {
+            return Err(BingError::from_result(ACTION, &resp_.result));
         }
+        let new_chat = Chat{
+            conversation_id: resp_.conversation_id,
+            chat_name: None,
+            conversation_signature: None,
+            tone: None,
+            create_time_utc: None,
+            update_time_utc:None,
+            plugins: Vec::new(),
+            x_sydney_conversationsignature:RwLock::new(x_sydney_conversationsignature),
+            x_sydney_encryptedconversationsignature:RwLock::new(x_sydney_encryptedconversationsignature),
+            signature_time: Some(std::time::Instant::now()).into(),
+            disengaged: Default::default(),
+            session: Default::default(), };
+        Ok(new_chat)
     }
 
-    pub async fn delete_chat(&self, chat: &Chat) -> Result<(), anyhow::Error> {
-        // if chat.x_sydney_conversationsignature.read().await.is_none() {
-            self.update_chat_signature(chat).await?;
-        // }
-        let mut headers = self.gen_header()?;
-        headers.insert(
-            "Authorization",
-            reqwest::header::HeaderValue::from_str(&format!(
-                "Bearer {}",
-                chat.x_sydney_conversationsignature
-                    .read()
-                    .await
-                    .clone()
-                    .unwrap()
-            ))?,
-        );
-        let request = self
-            .reqwest_client
-            .post(DELETE_CHAT_URL)
-            .headers(headers)
-            .json(&DeleteChatPayload::build(
-                &self.client_id,
-                &chat.conversation_id,
-            ));
-        let resp: DeleteChatResp = request.send().await?.json().await?;
-
-        if resp.result.value == "Success" {
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!(
-                "Delete Bing Copilot Chat Failed; ConversationId: {:?}; Error Value: {}; Error Message: {:?}",
-                resp.conversation_id,
-                resp.result.value,
-                resp.result.message
-            ))
-        }
+    pub async fn delete_chat(&self, chat: &Chat) -> Result<(), BingError> {
+        const ACTION: &str = "Delete Bing Copilot Chat";
+        self.with_chat_signature(chat, |headers| async move {
+            let request = self
+                .reqwest_client
+                .post(gen_delete_chat_url(&self.config.endpoints))
+                .headers(headers)
+                .json(&DeleteChatPayload::build(
+                    &self.client_id,
+                    &chat.conversation_id,
+                ));
+            let resp: DeleteChatResp = json_resp(ACTION, request.send().await?).await?;
+
+            if resp.result.value == "Success" {
+                Ok(())
+            } else {
+                Err(BingError::from_result(ACTION, &resp.result))
+            }
+        })
+        .await
     }
 
-    pub async fn delete_chats<'a>(&self, chats: TodelChats<'a>) -> Result<(), anyhow::Error> {
+    pub async fn delete_chats<'a>(&self, chats: TodelChats<'a>) -> Result<(), BingError> {
         let ids = match chats {
             TodelChats::Chats(chats) => chats
                 .iter()
@@ -352,79 +460,46 @@ impl BingClient {
 
         let request = self
             .reqwest_client
-            .post(DELETE_CHATS_URL)
+            .post(gen_delete_chats_url(&self.config.endpoints))
             .json(&DeleteChatsPayload::build(ids));
-        let resp: DeleteChatsResp = request.send().await?.json().await?;
+        const ACTION: &str = "Delete Bing Copilot Chats";
+        let resp: DeleteChatsResp = json_resp(ACTION, request.send().await?).await?;
 
         if resp.result.value == "Success" {
             Ok(())
         } else {
-            Err(anyhow::anyhow!(
-                "Delete Bing Copilot Chats Failed; Error Value: {}; Error Message: {:?}",
-                resp.result.value,
-                resp.result.message
-            ))
+            Err(BingError::from_result(ACTION, &resp.result))
         }
     }
 
-    pub async fn rename_chat(&self, chat: &Chat, new_name: String) -> Result<(), anyhow::Error> {
-        // if chat.x_sydney_conversationsignature.read().await.is_none() {
-            self.update_chat_signature(chat).await?;
-        // }
-        let mut headers = self.gen_header()?;
-        headers.insert(
-            "Authorization",
-            reqwest::header::HeaderValue::from_str(&format!(
-                "Bearer {}",
-                chat.x_sydney_conversationsignature
-                    .read()
-                    .await
-                    .clone()
-                    .unwrap()
-            ))?,
-        );
-        let request = self
-            .reqwest_client
-            .post(RENAME_CHAT_URL)
-            .headers(headers)
-            .json(&RenameChatRequest::build(
-                chat.conversation_id.to_string(),
-                self.client_id.to_string(),
-                new_name,
-            ));
-        let resp: RenameChatResp = request.send().await?.json().await?;
-
-        if resp.result.value == "Success" {
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!(
-                "Rename Bing Copilot Chat Failed; ConversationId: {}; Error Value: {}; Error Message: {:?}",
-                chat.conversation_id,
-                resp.result.value,
-                resp.result.message
-            ))
-        }
+    pub async fn rename_chat(&self, chat: &Chat, new_name: String) -> Result<(), BingError> {
+        const ACTION: &str = "Rename Bing Copilot Chat";
+        let new_name = &new_name;
+        self.with_chat_signature(chat, |headers| async move {
+            let request = self
+                .reqwest_client
+                .post(gen_rename_chat_url(&self.config.endpoints))
+                .headers(headers)
+                .json(&RenameChatRequest::build(
+                    chat.conversation_id.to_string(),
+                    self.client_id.to_string(),
+                    new_name.clone(),
+                ));
+            let resp: RenameChatResp = json_resp(ACTION, request.send().await?).await?;
+
+            if resp.result.value == "Success" {
+                Ok(())
+            } else {
+                Err(BingError::from_result(ACTION, &resp.result))
+            }
+        })
+        .await
     }
     pub async fn update_conversation(
         &self,
         chat: &Chat,
         new_message: Value,
-    ) -> Result<(), anyhow::Error> {
-        // if chat.x_sydney_conversationsignature.read().await.is_none() {
-            self.update_chat_signature(chat).await?;
-        // };
-        let mut headers = self.gen_header()?;
-        headers.insert(
-            "Authorization",
-            reqwest::header::HeaderValue::from_str(&format!(
-                "Bearer {}",
-                chat.x_sydney_conversationsignature
-                    .read()
-                    .await
-                    .clone()
-                    .unwrap()
-            ))?,
-        );
+    ) -> Result<(), BingError> {
         let payload = json!({
           "messages": [
               new_message
@@ -439,287 +514,231 @@ impl BingClient {
         }
         );
 
-        let resp: UpdateConversaionResp = self
-            .reqwest_client
-            .post(UPDATE_CONVERSATION_URL)
-            .headers(headers)
-            .json(&payload)
-            .send()
-            .await?
-            .json()
+        const ACTION: &str = "Update Bing Copilot Chat Message";
+        let payload = &payload;
+        self.with_chat_signature(chat, |headers| async move {
+            let resp: UpdateConversaionResp = json_resp(
+                ACTION,
+                self.reqwest_client
+                    .post(gen_update_conversation_url(&self.config.endpoints))
+                    .headers(headers)
+                    .json(payload)
+                    .send()
+                    .await?,
+            )
             .await?;
-        if resp.result.value == "Success" {
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!(
-                "Bing Copilot Update Chat Message Error; Error Value: {}; Error Message: {:?}",
-                resp.result.value,
-                resp.result.message
-            ))
-        }
+            if resp.result.value == "Success" {
+                Ok(())
+            } else {
+                Err(BingError::from_result(ACTION, &resp.result))
+            }
+        })
+        .await
     }
-    pub async fn get_chat_messages(&self, chat: &Chat) -> Result<Vec<EasyMsg>, anyhow::Error> {
-        // if chat.x_sydney_conversationsignature.read().await.is_none() {
-            self.update_chat_signature(chat).await?;
-        // }
-        let mut headers = self.gen_header()?;
-        headers.insert(
-            "Authorization",
-            reqwest::header::HeaderValue::from_str(&format!(
-                "Bearer {}",
-                chat.x_sydney_conversationsignature
-                    .read()
-                    .await
-                    .clone()
-                    .unwrap()
-            ))?,
-        );
-        let resp: Value = self
-            .reqwest_client
-            .get(gen_get_chat_messages_url(
-                &chat.conversation_id,
-                &self.client_id,
-            ))
-            .headers(headers)
-            .send()
-            .await?
-            .json()
+
+    // The whole history of a chat, with every message bing keeps
+    pub async fn get_conversation(&self, chat: &Chat) -> Result<Conversation, BingError> {
+        let conversation: Conversation = self
+            .with_chat_signature(chat, |headers| async move {
+                json_resp(
+                    "Get Bing Copilot Chat Messages",
+                    self.reqwest_client
+                        .get(gen_get_chat_messages_url(
+                            &self.config.endpoints,
+                            &chat.conversation_id,
+                            &self.client_id,
+                        ))
+                        .headers(headers)
+                        .send()
+                        .await?,
+                )
+                .await
+            })
             .await?;
-        process_chat_msgs(&resp, &self).await
+        // the history is what bing counts as the turns of the conversation
+        chat.set_turns(conversation.turns() as u32);
+        if let Some(max_turns) = conversation.max_turns() {
+            chat.set_max_turns(max_turns);
+        }
+        Ok(conversation)
+    }
+
+    // Returns without loading the images bing drew, their urls are in `pending_images`,
+    // see `resolve_images`
+    pub async fn get_chat_messages(&self, chat: &Chat) -> Result<Vec<EasyMsg>, BingError> {
+        let conversation = self.get_conversation(chat).await?;
+        Ok(process_chat_msgs(&conversation))
+    }
+
+    // Loads the `pending_images` of the messages, `config.image_concurrency` at a time.
+    // A failed image goes to `image_errors`.
+    pub async fn resolve_images(&self, messages: &mut [EasyMsg]) {
+        resolve_images(messages, self, self.config.image_concurrency).await
+    }
+
+    // Follows `ClientConfig.rollover` once `chat` reached bing's message limit,
+    // `None` if the chat can still be used
+    pub async fn rollover(&self, chat: &Chat) -> Result<Option<ChatRollover>, BingError> {
+        let (keep_messages, preamble) = match &self.config.rollover {
+            RolloverPolicy::Off => return Ok(None),
+            RolloverPolicy::NewChat {
+                keep_messages,
+                preamble,
+            } => (*keep_messages, preamble),
+        };
+        if !chat.session().is_limit_reached() {
+            return Ok(None);
+        }
+        let mut summary = String::new();
+        if keep_messages > 0 {
+            let messages = self.get_chat_messages(chat).await?;
+            let kept = &messages[messages.len().saturating_sub(keep_messages)..];
+            if !kept.is_empty() {
+                summary = preamble.clone();
+                for message in kept {
+                    summary += &format!("\n{}: {}", message.author, message.text);
+                }
+            }
+        }
+        let mut new_chat = self.create_chat().await?;
+        new_chat.tone = chat.tone.clone();
+        new_chat.plugins = chat.plugins.clone();
+        if !summary.is_empty() {
+            new_chat.set_seed(summary.clone());
+        }
+        Ok(Some(ChatRollover {
+            chat: Arc::new(new_chat),
+            previous_conversation_id: chat.conversation_id.clone(),
+            summary,
+        }))
+    }
+
+    // Rolls `chat` over before `user_input` is sent, the question then goes to the new chat
+    pub(crate) async fn rollover_question(
+        &self,
+        chat: &Chat,
+        user_input: &mut UserInput,
+    ) -> Result<Option<ChatRollover>, BingError> {
+        let rollover = self.rollover(chat).await?;
+        if let Some(rollover) = &rollover {
+            user_input.retarget(&rollover.chat);
+        }
+        Ok(rollover)
     }
 
     pub async fn draw_image(
         &self,
         prompt: &str,
-    ) -> Result<Vec<crate::types::bot_easy_resp_type::Image>, anyhow::Error> {
-        let url =
-            gen_pool_image_url(prompt, self.gen_header()?, &Uuid::new_v4().to_string()).await?;
-        let headers = self.gen_header()?;
-        poll_images(url, headers, true).await
+    ) -> Result<Vec<crate::types::bot_easy_resp_type::Image>, BingError> {
+        let url = gen_pool_image_url(
+            &self.no_redirect_client,
+            &self.config.endpoints,
+            prompt,
+            self.gen_header()?,
+            &Uuid::new_v4().to_string(),
+        )
+        .await?;
+        poll_images(&self.reqwest_client, &url, true).await
     }
 
+    // Yields the text as it streams, then the whole answer rendered by `MarkdownRenderer::default()`
     pub async fn ask_stream_plain<'a>(
         &'a self,
         chat: &'a Chat,
         user_input: UserInput,
-    ) -> Result<(Gen<String, (), impl Future<Output = ()> + 'a>, impl Fn()), anyhow::Error> {
+    ) -> Result<(Gen<String, (), impl Future<Output = ()> + 'a>, impl Fn()), BingError> {
         let (mut stream, stop_fn) = self.ask_stream(chat, user_input).await?;
-        let mut suggests: Vec<String> = Vec::new();
-        let mut images: Vec<crate::types::bot_easy_resp_type::Image> = Vec::new();
-        let mut sources: Vec<crate::types::bot_easy_resp_type::SourceAttribution> = Vec::new();
-        let mut limit_text = Vec::new();
-        let mut plain_text = String::new();
-        let mut apology_text = String::new();
+        let mut answer = BotAnswer::default();
         let chat_gen = Gen::new(|co| async move {
             while let GeneratorState::Yielded(data) = stream.async_resume().await {
-                match data {
-                    crate::types::bot_easy_resp_type::BotResp::Text(text) => {
-                        plain_text = text.to_owned();
-                        co.yield_(text).await;
-                    }
-                    crate::types::bot_easy_resp_type::BotResp::SuggestReply(mut suggest_vec) => {
-                        suggests.append(&mut suggest_vec);
-                    }
-                    crate::types::bot_easy_resp_type::BotResp::Image(mut image_vec) => {
-                        images.append(&mut image_vec);
-                    }
-                    crate::types::bot_easy_resp_type::BotResp::SourceAttribution(
-                        mut source_vec,
-                    ) => {
-                        sources.append(&mut source_vec);
-                    }
-                    crate::types::bot_easy_resp_type::BotResp::Limit(limit) => {
-                        limit_text.push(limit);
-                    }
-                    crate::types::bot_easy_resp_type::BotResp::Apology(error) => {
-                        apology_text += &(error + "\n");
-                    }
-                    _ => {}
+                // `TextMode::Delta`, `TextMode::Both` also sends the whole text
+                let updated = matches!(data, BotResp::Text(_))
+                    || (matches!(data, BotResp::TextDelta(_))
+                        && self.config.text_mode == TextMode::Delta);
+                answer.push(data);
+                if updated {
+                    co.yield_(answer.text.clone()).await;
                 }
             }
-            let mut result = plain_text;
-            if !images.is_empty() {
-                result += "\nImages:\n\n";
-                for (index, image) in images.iter().enumerate() {
-                    result += &format!("{}. {}\n", index + 1, image);
-                }
-            }
-            if !apology_text.is_empty() {
-                result += "\nApology: \n\n";
-                result += &apology_text;
-            }
-            if !sources.is_empty() {
-                result += "\nSources:\n\n";
-                for (index, source) in sources.iter().enumerate() {
-                    result += &format!("{}. {}\n", index + 1, source);
-                }
-            }
-            if !suggests.is_empty() {
-                result += "\nSuggest Replys:\n\n";
-                for (index, suggest) in suggests.iter().enumerate() {
-                    result += &format!("{}. {}\n", index + 1, suggest);
-                }
-            }
-            if !limit_text.is_empty() {
-                result += "\nLimits:\n\n";
-                for (index, limit) in limit_text.iter().enumerate() {
-                    result += &format!("{}. {}\n", index + 1, limit);
-                }
-            }
-            co.yield_(result).await;
+            co.yield_(MarkdownRenderer::default().render(&answer)).await;
         });
         Ok((chat_gen, stop_fn))
     }
 
+    // A chat at the message limit is rolled over first, see `RolloverPolicy`
     pub async fn ask_stream<'a>(
         &'a self,
         chat: &'a Chat,
-        user_input: UserInput,
-    ) -> Result<(Gen<BotResp, (), impl Future<Output = ()> + 'a>, impl Fn()), anyhow::Error> {
-        // if chat
-        //     .x_sydney_encryptedconversationsignature
-        //     .read()
-        //     .await
-        //     .is_none()
-        // {
-            self.update_chat_signature(chat).await?;
-        // }
-        let url = gen_chat_hub_wss_url(
-            chat.x_sydney_encryptedconversationsignature
-                .read()
-                .await
-                .as_ref()
-                .unwrap(),
-        );
-
-        let mut request = url.into_client_request()?;
-        request.headers_mut().insert(
-            http::header::COOKIE,
-            HeaderValue::from_str(&self.cookie_str.clone())?,
-        );
-
-        let (ws_stram, _msg) = connect_async(request).await?;
-        let (mut write, mut read) = ws_stram.split();
-
-        let handshake_msg = add_suffix(json!({"protocol":"json","version":1}).to_string());
-        let echo_msg = add_suffix(json!({"type":6}).to_string());
-        let stop_msg = add_suffix(
-            json!({"arguments":[{}],"invocationId":"3","target":"stop","type":1}).to_string(),
-        );
-
-        let signal = StopSignal::new();
-        let stop_fn = signal.stop_fn();
-
-        write.send(Text(handshake_msg)).await?;
-        read.next().await;
-        write.send(Text(echo_msg.clone())).await?;
-        write
-            .send(Text(serde_json::to_string(&user_input)?))
+        mut user_input: UserInput,
+    ) -> Result<(Gen<BotResp, (), impl Future<Output = ()> + 'a>, impl Fn()), BingError> {
+        let rollover = self.rollover_question(chat, &mut user_input).await?;
+        let new_chat = rollover.as_ref().map(|rollover| rollover.chat.clone());
+        let mut connection = self
+            .open_chat_hub(new_chat.as_deref().unwrap_or(chat), &user_input)
             .await?;
+        let mut signal = StopSignal::new();
+        let stop_fn = signal.stop_fn();
+        let (tx, mut rx) = mpsc::channel::<Result<BotResp, BingError>>(1);
 
         let chat_gen = Gen::new(|co| async move {
-            let mut botresp_tasks_handle: Vec<tokio::task::JoinHandle<(BotResp, Value)>> =
-                Vec::new();
-            let mut shutdown = false;
-            while let Some(ws_msg_rst) = read.next().await {
-                if signal.check_stop() {
-                    write.send(Text(stop_msg.clone())).await.unwrap();
-                }
-                match ws_msg_rst {
-                    Ok(ws_msg) => match ws_msg {
-                        Text(texts) => {
-                            let texts: Vec<&str> = texts
-                                .trim_end_matches("\x1e")
-                                .split("\x1e")
-                                .filter(|s| s.len() > 0)
-                                .collect();
-
-                            for text in texts {
-                                match serde_json::Value::from_str(text) {
-                                    Ok(json) => {
-                                        if let Some(type_num) = json["type"].as_u64() {
-                                            match type_num {
-                                                1 => {
-                                                    for botresp in json2bot_resp_type1(
-                                                        &json,
-                                                        &mut botresp_tasks_handle,
-                                                        &self,
-                                                    ) {
-                                                        co.yield_(botresp).await;
-                                                    }
-                                                }
-                                                // 如果正常回答完毕，则最后消息为2类型
-                                                2 => {
-                                                    if let Ok(bot_resps) =
-                                                        json2bot_resp_type2(&json)
-                                                    {
-                                                        for botresp in bot_resps {
-                                                            co.yield_(botresp).await;
-                                                        }
-                                                    }
-                                                    shutdown = true;
-                                                    break;
-                                                }
-                                                // 如果用户取消回答，则最后消息为3类型
-                                                3 => {
-                                                    shutdown = true;
-                                                    break;
-                                                }
-                                                // 6消息类型为心跳数据，我们直接跟随服务器端进行心跳即可
-                                                6 => {
-                                                    let _ =
-                                                        write.send(Text(echo_msg.clone())).await;
-                                                }
-
-                                                _ => {}
-                                            }
-                                        }
-                                    }
-                                    Err(_) => {}
-                                }
-                            }
-                            if shutdown {
-                                break;
-                            }
-                        }
-                        tokio_tungstenite::tungstenite::Message::Close(_) => {
-                            break;
-                        }
-                        _ => {
-                            continue;
-                        }
-                    },
-                    Err(e) => match e {
-                        tokio_tungstenite::tungstenite::Error::ConnectionClosed => break,
-                        tokio_tungstenite::tungstenite::Error::AlreadyClosed => break,
-                        _ => continue,
-                    },
-                }
+            let chat = new_chat.as_deref().unwrap_or(chat);
+            if let Some(rollover) = rollover {
+                co.yield_(BotResp::Rollover(rollover)).await;
             }
-            let resp_results: Vec<Result<(BotResp, Value), JoinError>> =
-                join_all(botresp_tasks_handle).await;
-            for resp_result in resp_results {
-                match resp_result {
-                    Ok((resp, payload)) => {
-                        co.yield_(resp).await;
-                        match self.update_conversation(chat, payload).await {
-                            Ok(()) => {}
-                            Err(e) => {
-                                co.yield_(BotResp::Apology(format!(
-                                    "Failed to update conversation: {e}"
-                                )))
-                                .await;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        co.yield_(BotResp::Apology(format!("{}", e))).await;
+            let forward = async {
+                while let Some(resp) = rx.recv().await {
+                    match resp {
+                        Ok(resp) => co.yield_(resp).await,
+                        Err(e) => co.yield_(BotResp::Error(AnswerError::from(&e))).await,
                     }
                 }
-            }
+            };
+            tokio::join!(
+                self.read_answer(chat, &mut connection, &user_input, &mut signal, tx, false),
+                forward
+            );
         });
         Ok((chat_gen, stop_fn))
     }
+
+    // Waits for the whole answer. A broken frame is skipped, a failed image goes to `errors`.
+    // Any other error is returned as `BingError::PartialAnswer` with the answer so far.
+    // A chat at the message limit is rolled over first, see `BotAnswer.rollover`.
+    pub async fn ask(
+        &self,
+        chat: &Chat,
+        mut user_input: UserInput,
+    ) -> Result<BotAnswer, BingError> {
+        let rollover = self.rollover_question(chat, &mut user_input).await?;
+        let chat = rollover.as_ref().map_or(chat, |rollover| &rollover.chat);
+        let mut connection = self.open_chat_hub(chat, &user_input).await?;
+        let mut signal = StopSignal::new();
+        let (tx, mut rx) = mpsc::channel::<Result<BotResp, BingError>>(CHANNEL_SIZE);
+        let mut answer = BotAnswer {
+            rollover: rollover.clone(),
+            ..Default::default()
+        };
+        let mut error = None;
+        let collect = async {
+            while let Some(item) = rx.recv().await {
+                answer.push_item(item, &mut error);
+            }
+        };
+        tokio::join!(
+            self.read_answer(chat, &mut connection, &user_input, &mut signal, tx, false),
+            collect
+        );
+        answer.finish(error, false)
+    }
+
+    // Like `ask_stream`, but the returned stream is `Send + 'static`
+    pub async fn ask_stream_owned(
+        self: Arc<Self>,
+        chat: Arc<Chat>,
+        user_input: UserInput,
+    ) -> Result<AskStream, BingError> {
+        AskStream::new(self, chat, user_input).await
+    }
 }
 
 impl<'de> Deserialize<'de> for BingClient {
@@ -729,8 +748,11 @@ impl<'de> Deserialize<'de> for BingClient {
     {
         enum Field {
             CookieStr,
+            Cookies,
+            CookieSource,
             ClientId,
             Chats,
+            Config,
         }
 
         impl<'de> Deserialize<'de> for Field {
@@ -744,7 +766,9 @@ impl<'de> Deserialize<'de> for BingClient {
                     type Value = Field;
 
                     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                        formatter.write_str("`cookie_str`, `client_id` or `chats`")
+                        formatter.write_str(
+                            "`cookies`, `cookie_source`, `cookie_str`, `client_id`, `chats` or `config`",
+                        )
                     }
 
                     fn visit_str<E>(self, value: &str) -> Result<Field, E>
@@ -753,8 +777,11 @@ impl<'de> Deserialize<'de> for BingClient {
                     {
                         match value {
                             "cookie_str" => Ok(Field::CookieStr),
+                            "cookies" => Ok(Field::Cookies),
+                            "cookie_source" => Ok(Field::CookieSource),
                             "client_id" => Ok(Field::ClientId),
                             "chats" => Ok(Field::Chats),
+                            "config" => Ok(Field::Config),
                             _ => Err(de::Error::unknown_field(value, FIELDS)),
                         }
                     }
@@ -778,8 +805,11 @@ impl<'de> Deserialize<'de> for BingClient {
                 V: MapAccess<'de>,
             {
                 let mut cookie_str: Option<String> = None;
+                let mut cookies: Option<Vec<JsonCookie>> = None;
+                let mut cookie_source: Option<Option<Cookie>> = None;
                 let mut client_id = None;
                 let mut chats = None;
+                let mut config: Option<ClientConfig> = None;
                 while let Some(key) = map.next_key()? {
                     match key {
                         Field::CookieStr => {
@@ -788,6 +818,18 @@ impl<'de> Deserialize<'de> for BingClient {
                             }
                             cookie_str = Some(map.next_value()?);
                         }
+                        Field::Cookies => {
+                            if cookies.is_some() {
+                                return Err(de::Error::duplicate_field("cookies"));
+                            }
+                            cookies = Some(map.next_value()?);
+                        }
+                        Field::CookieSource => {
+                            if cookie_source.is_some() {
+                                return Err(de::Error::duplicate_field("cookie_source"));
+                            }
+                            cookie_source = Some(map.next_value()?);
+                        }
                         Field::ClientId => {
                             if client_id.is_some() {
                                 return Err(de::Error::duplicate_field("client_id"));
@@ -800,49 +842,55 @@ impl<'de> Deserialize<'de> for BingClient {
                             }
                             chats = Some(map.next_value()?);
                         }
+                        Field::Config => {
+                            if config.is_some() {
+                                return Err(de::Error::duplicate_field("config"));
+                            }
+                            config = Some(map.next_value()?);
+                        }
                     }
                 }
-                let cookie_str =
-                    cookie_str.ok_or_else(|| de::Error::missing_field("cookie_str"))?;
                 let client_id = client_id.ok_or_else(|| de::Error::missing_field("client_id"))?;
                 let chats = chats.ok_or_else(|| de::Error::missing_field("chats"))?;
+                // 旧版本序列化的数据没有 config 字段
+                let config = config.unwrap_or_default();
+                // 旧版本序列化的数据只有 cookie_str 字段
+                let cookie_jar = match (cookies, cookie_str) {
+                    (Some(cookies), _) => CookieJar::from_json_cookies(cookies),
+                    (None, Some(cookie_str)) => {
+                        CookieJar::from_header_str(&cookie_str, &config.endpoints)
+                            .map_err(de::Error::custom)?
+                    }
+                    (None, None) => return Err(de::Error::missing_field("cookies")),
+                };
 
                 // 创建ReqwestClient实例
-                let mut headers = HeaderMap::new();
-                headers.insert(
-                    reqwest::header::COOKIE,
-                    cookie_str.parse().map_err(de::Error::custom)?,
-                );
-                headers.insert(
-                    "Referer",
-                    "https://www.bing.com/search?q=Bing+Ai"
-                        .parse()
-                        .map_err(de::Error::custom)?,
-                );
-
-                #[cfg(feature = "allow-invalid-tls")]
-                let reqwest_client = ReqwestClient::builder()
-                    .default_headers(headers)
-                    .danger_accept_invalid_certs(true)
-                    .build()
-                    .map_err(de::Error::custom)?;
-
-                #[cfg(not(feature = "allow-invalid-tls"))]
-                let reqwest_client = ReqwestClient::builder()
-                    .default_headers(headers)
-                    .build()
-                    .map_err(de::Error::custom)?;
-
-                Ok(BingClient {
-                    reqwest_client,
-                    cookie_str,
+                BingClient::from_parts(
+                    cookie_jar,
+                    cookie_source.flatten(),
                     client_id,
                     chats,
-                })
+                    config,
+                )
+                .map_err(de::Error::custom)
             }
         }
 
-        const FIELDS: &'static [&'static str] = &["cookie_str", "client_id", "chats"];
+        const FIELDS: &[&str] = &[
+            "cookies",
+            "cookie_source",
+            "cookie_str",
+            "client_id",
+            "chats",
+            "config",
+        ];
         deserializer.deserialize_struct("Client", FIELDS, ClientVisitor)
     }
 }
+
+// Cookies come from `BingClient.cookie_jar`
+fn gen_referer_header(config: &ClientConfig) -> Result<HeaderMap, BingError> {
+    let mut headers = HeaderMap::new();
+    headers.insert("Referer", gen_referer_url(&config.endpoints).parse()?);
+    Ok(headers)
+}
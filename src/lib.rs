@@ -5,12 +5,34 @@ pub mod tests;
 pub mod types;
 pub mod utils;
 
+pub use client::ask_stream::AskStream;
+pub use client::builder::BingClientBuilder;
+pub use client::chat_session::ChatSession;
 pub use client::BingClient;
-pub use types::chat_msg_type::EasyMsg;
+pub use types::bot_answer_type::{BotAnswer, FinishStatus};
+pub use types::client_config_type::{
+    ClientConfig, Endpoints, ProxyAuth, ProxyConfig, RolloverPolicy, TextMode, TlsMode,
+};
+pub use types::chat_msg_type::{EasyMsg, ImageError};
+pub use types::chat_timeout_type::{ChatTimeouts, TimeoutPhase};
 pub use types::chat_type::Chat;
+pub use types::conversation_type::{ChatMessage, Conversation, Feedback};
+pub use types::citation_type::{Citation, CitedText};
 pub use types::cookie_type::Cookie;
+pub use types::cookie_report_type::{CookieCheck, CookieReport, ExpiredCookie, ForeignCookie};
 pub use types::delete_chat_type::TodelChats;
+pub use types::bot_easy_resp_type::AnswerError;
+pub use types::error_type::{BingError, ErrorKind};
+pub use types::locale_type::{Locale, Location};
 pub use types::plugin_type::Plugin;
+pub use types::render_type::{LinkStyle, MarkdownFlavor, RenderOptions, Section, SectionHeadings};
+pub use types::rollover_type::ChatRollover;
+pub use types::stop_outcome_type::{StopOutcome, StopStatus};
 pub use types::user_input_type::Tone;
 pub use types::user_input_type::UserInput;
+pub use utils::answer_render::{
+    AnswerRenderer, HtmlRenderer, JsonRenderer, MarkdownRenderer, PlainRenderer,
+};
+pub use utils::cookie_jar::CookieJar;
 pub use utils::image_base64::Image;
+pub use utils::stop_signal::StopHandle;
@@ -2,11 +2,7 @@
 macro_rules! vec_string {
     ($($element:expr),*) => {
         {
-            let mut temp_vec = Vec::new();
-            $(
-                temp_vec.push($element.to_string());
-            )*
-            temp_vec
+            vec![$($element.to_string()),*]
         }
     };
 }
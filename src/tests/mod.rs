@@ -16,7 +16,7 @@ mod test {
             BingClient::build_with_chats(&Cookie::JsonPath("_data/cookie.json".to_string()))
                 .await
                 .unwrap();
-        println!("Client cookie: {}", client.cookie_str);
+        println!("Client cookie: {}", client.cookie_str());
         println!("Client id: {}", client.client_id);
         println!("Total {} chats got.", client.chats.len());
         for (index, chat) in client.chats.iter().enumerate() {
@@ -26,12 +26,11 @@ mod test {
 
     #[tokio::test]
     async fn test_build_client_with_str() {
-        let client = BingClient::build_with_chats(&Cookie::HeadStr(
-            include_str!(r#"D:\Git\bing_client\_data\cookie"#).to_string(),
-        ))
-        .await
-        .unwrap();
-        println!("Client cookie: {}", client.cookie_str);
+        let cookie = tokio::fs::read_to_string("_data/cookie").await.unwrap();
+        let client = BingClient::build_with_chats(&Cookie::HeadStr(cookie))
+            .await
+            .unwrap();
+        println!("Client cookie: {}", client.cookie_str());
         println!("Client id: {}", client.client_id);
         println!("Total {} chats got.", client.chats.len());
         for (index, chat) in client.chats.iter().enumerate() {
@@ -55,7 +54,7 @@ mod test {
                 .await
                 .unwrap();
         let last_chat = client.chats.last().unwrap();
-        match client.delete_chat(& last_chat).await {
+        match client.delete_chat(last_chat).await {
             Ok(_) => {
                 println!("删除成功")
             }
@@ -91,7 +90,7 @@ mod test {
             .unwrap();
         // let last_chat = client.chats.first().unwrap();
         let last_chat = client.chats.iter().find(|chat|{chat.chat_name.as_ref().unwrap().contains("画")}).unwrap();
-        match client.get_chat_messages(& last_chat).await {
+        match client.get_chat_messages(last_chat).await {
             Ok(value) => {
                 println!("成功获取 chat 的messages: {:#?}", value);
                 let mut file = tokio::fs::File::create("./_data/msgs.json").await.unwrap();
@@ -113,7 +112,7 @@ mod test {
                 .unwrap();
         let last_chat = client.chats.first().unwrap();
         client
-            .rename_chat(& last_chat, "1234".to_string())
+            .rename_chat(last_chat, "1234".to_string())
             .await
             .unwrap();
     }
@@ -230,12 +229,34 @@ mod test {
             print!("\x1b[2J\x1b[H");
             match data {
                 crate::types::bot_easy_resp_type::BotResp::Text(text) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::TextDelta(delta) => todo!(),
                 crate::types::bot_easy_resp_type::BotResp::SuggestReply(suggest_replys) => todo!(),
                 crate::types::bot_easy_resp_type::BotResp::Notice(notice) => todo!(),
                 crate::types::bot_easy_resp_type::BotResp::Image(images) => todo!(),
                 crate::types::bot_easy_resp_type::BotResp::Apology(apology) => todo!(),
                 crate::types::bot_easy_resp_type::BotResp::SourceAttribution(sources) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::Citations(cited) => todo!(),
                 crate::types::bot_easy_resp_type::BotResp::Limit(limit) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::SearchQuery(query) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::SearchResults(results) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::Disengaged(text) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::Progress(text) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::Tasks(tasks) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::GeneratedCode(code) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::RenderCard(text) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::Disclaimer(text) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::Other { message_type, text } => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::Retracted(withdrawal) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::Moderated(withdrawal) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::Error(error) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::RenderContent(text) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::ActionRequest(text) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::ConfirmationCard(text) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::Context(text) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::AdsQuery(query) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::SemanticSerp(text) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::SongPrompt(prompt) => todo!(),
+                crate::types::bot_easy_resp_type::BotResp::Rollover(rollover) => todo!(),
             }
         }
     }
@@ -259,7 +280,7 @@ mod test {
         let client = serde_json::from_str::<BingClient>(&client_str).unwrap();
         let last_chat = client.chats.last().unwrap();
         println!("{:?}", last_chat);
-        client.delete_chat(& last_chat).await.unwrap();
+        client.delete_chat(last_chat).await.unwrap();
     }
     
     #[tokio::test]
@@ -277,4 +298,2321 @@ mod test {
         let json = serde_json::to_string(&userinput).unwrap();
         println!("{}",json);
     }
+
+    #[tokio::test]
+    async fn test_ws_http_proxy_tunnel() {
+        use tokio::{
+            io::AsyncReadExt,
+            net::{TcpListener, TcpStream},
+        };
+
+        let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target_addr = target.local_addr().unwrap();
+        let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = target.accept().await.unwrap();
+            socket.write_all(b"hello").await.unwrap();
+        });
+        let proxy_task = tokio::spawn(async move {
+            let (mut socket, _) = proxy.accept().await.unwrap();
+            let mut buf = vec![0u8; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            let connect_req = String::from_utf8_lossy(&buf[..n]).to_string();
+            let mut upstream = TcpStream::connect(target_addr).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = tokio::io::copy_bidirectional(&mut socket, &mut upstream).await;
+            connect_req
+        });
+
+        let config = crate::ClientConfig {
+            proxy: crate::ProxyConfig {
+                all: Some(format!("http://user:pass@{proxy_addr}")),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut stream =
+            crate::utils::connect_ws::open_stream(&config, "wss", "127.0.0.1", target_addr.port())
+                .await
+                .unwrap();
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+        drop(stream);
+
+        let connect_req = proxy_task.await.unwrap();
+        assert!(connect_req.starts_with(&format!("CONNECT {target_addr} HTTP/1.1\r\n")));
+        // base64("user:pass")
+        assert!(connect_req.contains("Proxy-Authorization: Basic dXNlcjpwYXNz\r\n"));
+
+        // an https proxy gets a tls handshake before the CONNECT
+        let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        let proxy_task = tokio::spawn(async move {
+            let (mut socket, _) = proxy.accept().await.unwrap();
+            let mut record_type = [0u8; 1];
+            socket.read_exact(&mut record_type).await.unwrap();
+            record_type[0]
+        });
+        let config = crate::ClientConfig {
+            proxy: crate::ProxyConfig {
+                all: Some(format!("https://localhost:{}", proxy_addr.port())),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let rst =
+            crate::utils::connect_ws::open_stream(&config, "wss", "127.0.0.1", target_addr.port())
+                .await;
+        assert!(!matches!(rst, Err(crate::BingError::Proxy(e)) if e.contains("Unsupported")));
+        // a tls handshake record
+        assert_eq!(proxy_task.await.unwrap(), 0x16);
+    }
+
+    #[tokio::test]
+    async fn test_image_url_follows_redirects() {
+        use tokio::{io::AsyncReadExt, net::TcpListener};
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(2, 2)
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        let png = png.into_inner();
+
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let server_task = tokio::spawn(async move {
+            let mut paths = Vec::new();
+            for _ in 0..2 {
+                let (mut socket, _) = server.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap();
+                let req = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = req.split_whitespace().nth(1).unwrap().to_string();
+                if path == "/start" {
+                    socket
+                        .write_all(b"HTTP/1.1 302 Found\r\nLocation: /image.png\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                        .await
+                        .unwrap();
+                } else {
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        png.len()
+                    );
+                    socket.write_all(head.as_bytes()).await.unwrap();
+                    socket.write_all(&png).await.unwrap();
+                }
+                paths.push(path);
+            }
+            paths
+        });
+
+        let client = crate::utils::http_client::build_reqwest_client(
+            &crate::ClientConfig::default(),
+            reqwest::header::HeaderMap::new(),
+            false,
+            None,
+        )
+        .unwrap();
+        let encoded = crate::Image::Url(format!("http://{addr}/start"))
+            .to_base64(&client)
+            .await
+            .unwrap();
+        assert!(!encoded.is_empty());
+        assert_eq!(server_task.await.unwrap(), vec!["/start", "/image.png"]);
+    }
+
+    #[tokio::test]
+    async fn test_cookie_jar_absorbs_set_cookie() {
+        use std::sync::Arc;
+        use tokio::{io::AsyncReadExt, net::TcpListener};
+
+        use crate::{types::client_config_type::Endpoints, utils::cookie_jar::CookieJar};
+
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let server_task = tokio::spawn(async move {
+            let (mut socket, _) = server.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nSet-Cookie: _RwBf=new; Path=/; Max-Age=3600\r\nSet-Cookie: old=; Max-Age=0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+
+        let endpoints = Endpoints {
+            www: format!("http://{addr}"),
+            ..Default::default()
+        };
+        let jar = Arc::new(CookieJar::from_header_str("_U=abc; old=1", &endpoints).unwrap());
+        let client = crate::utils::http_client::build_reqwest_client(
+            &crate::ClientConfig::default(),
+            reqwest::header::HeaderMap::new(),
+            true,
+            Some(jar.clone()),
+        )
+        .unwrap();
+        client.get(&endpoints.www).send().await.unwrap();
+
+        let url = reqwest::Url::parse(&endpoints.www).unwrap();
+        let sorted = |header: String| {
+            let mut pairs: Vec<String> = header.split("; ").map(str::to_string).collect();
+            pairs.sort();
+            pairs
+        };
+        let request = server_task.await.unwrap();
+        let sent = request
+            .lines()
+            .find_map(|line| line.strip_prefix("cookie: "))
+            .unwrap_or_default();
+        assert_eq!(sorted(sent.to_string()), vec!["_U=abc", "old=1"]);
+        assert_eq!(sorted(jar.cookie_header(&url)), vec!["_RwBf=new", "_U=abc"]);
+
+        // round trip through the `JsonPath` format
+        let json = serde_json::to_string(&jar.to_json_cookies()).unwrap();
+        let reloaded = CookieJar::from_json_cookies(serde_json::from_str(&json).unwrap());
+        assert_eq!(sorted(reloaded.cookie_header(&url)), vec!["_RwBf=new", "_U=abc"]);
+        assert!(reloaded
+            .to_json_cookies()
+            .iter()
+            .any(|cookie| cookie.name == "_RwBf" && cookie.expiration_date.is_some()));
+    }
+
+    #[test]
+    fn test_message_locale() {
+        use crate::types::{
+            locale_type::{Locale, Location},
+            user_input_type::Message,
+        };
+        let locale = Locale::new("de-DE");
+        let message = Message::build("hallo".to_string(), None, "id".to_string(), &locale);
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(value["locale"], "de-DE");
+        assert_eq!(value["market"], "de-DE");
+        assert_eq!(value["region"], "DE");
+        assert!(value.get("location").is_none());
+        assert!(value.get("locationHints").is_none());
+        assert!(value.get("userIpAddress").is_none());
+        assert_eq!(locale.edge_cd_cookie(), "_EDGE_CD=m=de-de&u=de-de");
+
+        let locale = Locale::default().with_user_ip_address("1.2.3.4");
+        let message = Message::build("hello".to_string(), None, "id".to_string(), &locale);
+        let value = serde_json::to_value(&message).unwrap();
+        let redmond = Location::redmond();
+        assert_eq!(
+            value["location"],
+            format!("lat:{};long:{};re=1000m;", redmond.latitude, redmond.longitude)
+        );
+        assert_eq!(value["locationHints"][0]["PopulatedPlaceName"], "Redmond");
+        assert_eq!(value["userIpAddress"], "1.2.3.4");
+
+        // the region comes from the region subtag or the language
+        let locale = Locale::new("en");
+        assert_eq!((locale.market.as_str(), locale.region.as_str()), ("en-US", "US"));
+        assert!(locale.validate().is_ok());
+        let locale = Locale::new("zh-Hans-CN");
+        assert_eq!((locale.market.as_str(), locale.region.as_str()), ("zh-Hans-CN", "CN"));
+        let locale = Locale::new("es-419");
+        assert_eq!(locale.region, "419");
+        assert!(locale.validate().is_ok());
+        let locale = Locale::new("xx");
+        assert!(matches!(locale.validate(), Err(crate::BingError::Config(_))));
+        assert!(locale
+            .with_region("GB")
+            .with_market("en-GB")
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_request_locale_cookie() {
+        use crate::{
+            types::locale_type::Locale,
+            utils::{connect_ws::build_chat_hub_request, cookie_jar::CookieJar},
+            ClientConfig,
+        };
+
+        let config = ClientConfig::default();
+        let jar = CookieJar::from_header_str(
+            "_U=abc; _EDGE_CD=m=en-us&u=en-us",
+            &config.endpoints,
+        )
+        .unwrap();
+        let url = "wss://sydney.bing.com/sydney/ChatHub";
+        let cookie = |locale: Option<&Locale>| {
+            build_chat_hub_request(&config, url, &jar, locale).unwrap().headers()
+                [http::header::COOKIE]
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+        assert!(cookie(None).contains("_EDGE_CD=m=en-us&u=en-us"));
+        let french = cookie(Some(&Locale::new("fr-FR")));
+        assert!(french.contains("_U=abc"));
+        assert!(french.contains("_EDGE_CD=m=fr-fr&u=fr-fr"));
+        assert!(!french.contains("en-us"));
+    }
+
+    #[test]
+    fn test_error_mapping() {
+        use crate::types::error_type::BingError;
+        assert!(matches!(
+            BingError::from_status("Test", 401, String::new()),
+            BingError::Unauthorized {
+                status: Some(401),
+                message: None,
+                ..
+            }
+        ));
+        assert!(matches!(
+            BingError::from_status("Test", 429, "slow down".to_string()),
+            BingError::Throttled {
+                status: Some(429),
+                ..
+            }
+        ));
+        assert!(matches!(
+            BingError::from_status("Test", 200, "<html>Captcha</html>".to_string()),
+            BingError::CaptchaChallenge { .. }
+        ));
+        assert_eq!(
+            BingError::from_status("Test", 500, "oops".to_string()).status(),
+            Some(500)
+        );
+        assert!(matches!(
+            BingError::from_service("Test", "UnauthorizedRequest", None),
+            BingError::Unauthorized { status: None, .. }
+        ));
+        assert!(matches!(
+            BingError::from_service("Test", "InvalidSession", None),
+            BingError::InvalidSession { .. }
+        ));
+        match BingError::from_service("Test", "Unknown", Some("msg".to_string())) {
+            BingError::Service { value, message, .. } => {
+                assert_eq!(value, "Unknown");
+                assert_eq!(message.as_deref(), Some("msg"));
+            }
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cookie_report() {
+        use crate::{
+            types::{cookie_report_type::CookieCheck, error_type::BingError},
+            utils::cookie_pre::load_cookie_jar,
+            ClientConfig, Cookie,
+        };
+        let json = r#"[
+            {"domain": ".bing.com", "name": "_U", "value": "old", "expirationDate": 1000000000},
+            {"domain": "www.bing.com", "name": "MUID", "value": "1", "session": true},
+            {"domain": ".example.com", "name": "other", "value": "2", "session": true}
+        ]"#;
+        let config = ClientConfig::default();
+        let (jar, report) = load_cookie_jar(&Cookie::JsonStr(json.to_string()), &config)
+            .await
+            .unwrap();
+        assert!(!jar.contains("_U"));
+        // a foreign cookie is not sent anywhere
+        assert!(!jar.contains("other"));
+        assert!(report.missing.is_empty());
+        assert_eq!(report.expired.len(), 1);
+        assert!(report.expired[0].required);
+        assert_eq!(report.foreign.len(), 1);
+        assert_eq!(report.foreign[0].name, "other");
+        assert!(!report.is_valid());
+
+        let (_, report) = load_cookie_jar(&Cookie::HeadStr("MUID=1".to_string()), &config)
+            .await
+            .unwrap();
+        assert_eq!(report.missing, vec!["_U"]);
+
+        let strict = ClientConfig {
+            cookie_check: CookieCheck::Strict,
+            ..Default::default()
+        };
+        assert!(matches!(
+            load_cookie_jar(&Cookie::JsonStr(json.to_string()), &strict).await,
+            Err(BingError::InvalidCookies(_))
+        ));
+        let (_, report) = load_cookie_jar(&Cookie::HeadStr("_U=abc".to_string()), &strict)
+            .await
+            .unwrap();
+        assert!(report.is_clean());
+
+        let off = ClientConfig {
+            cookie_check: CookieCheck::Off,
+            ..Default::default()
+        };
+        let (jar, report) = load_cookie_jar(&Cookie::JsonStr(json.to_string()), &off)
+            .await
+            .unwrap();
+        assert!(report.is_clean());
+        assert!(!jar.contains("other"));
+
+        // a cookie that expires after the build is reported like one that expired before
+        let client = mock_bing(vec![]).await;
+        assert!(client.check_cookies().is_clean());
+        let url = reqwest::Url::parse(&client.config.endpoints.www).unwrap();
+        let set_cookie = http::HeaderValue::from_static("_U=short; Max-Age=1; Path=/");
+        client
+            .cookie_jar
+            .store_set_cookies([&set_cookie].into_iter(), &url);
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let report = client.check_cookies();
+        assert_eq!(report.expired.len(), 1);
+        assert_eq!(report.expired[0].name, "_U");
+        assert!(report.expired[0].required);
+        assert!(report.missing.is_empty());
+        assert!(!report.is_valid());
+
+        // the report of the build is kept as it is
+        let mut client = mock_bing(vec![]).await;
+        client.config.cookie_check = CookieCheck::Off;
+        client.config.required_cookies.push("absent".to_string());
+        assert!(client.check_cookies().is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_netscape_and_har_cookies() {
+        use crate::{
+            utils::{cookie_import::to_netscape, cookie_pre::load_cookie_jar},
+            ClientConfig, Cookie,
+        };
+        let config = ClientConfig::default();
+        let url = reqwest::Url::parse(&config.endpoints.www).unwrap();
+        let sorted = |header: String| {
+            let mut pairs: Vec<String> = header.split("; ").map(str::to_string).collect();
+            pairs.sort();
+            pairs
+        };
+
+        let netscape = include_str!("fixtures/cookies.txt");
+        let (jar, report) = load_cookie_jar(&Cookie::NetscapeStr(netscape.to_string()), &config)
+            .await
+            .unwrap();
+        assert_eq!(
+            sorted(jar.cookie_header(&url)),
+            vec![
+                "MUID=muid\tvalue",
+                "_EDGE_CD=m=en-us&u=en-us",
+                "_U=user-token"
+            ]
+        );
+        assert_eq!(report.expired.len(), 1);
+        assert!(!report.expired[0].required);
+        assert!(report.foreign.is_empty());
+        assert!(jar
+            .to_json_cookies()
+            .iter()
+            .any(|cookie| cookie.name == "MUID" && cookie.http_only && cookie.host_only));
+
+        // round trip through `save_cookies_to(&Cookie::NetscapePath(..))`
+        let saved = to_netscape(&jar.to_json_cookies());
+        let (reloaded, _) = load_cookie_jar(&Cookie::NetscapeStr(saved), &config)
+            .await
+            .unwrap();
+        assert_eq!(
+            sorted(reloaded.cookie_header(&url)),
+            sorted(jar.cookie_header(&url))
+        );
+
+        let har = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/tests/fixtures/cookies.har"
+        );
+        let (jar, report) = load_cookie_jar(&Cookie::HarPath(har.to_string()), &config)
+            .await
+            .unwrap();
+        assert_eq!(
+            sorted(jar.cookie_header(&url)),
+            vec!["_EDGE_CD=m=en-us&u=en-us", "_RwBf=new", "_U=user-token"]
+        );
+        assert!(report.is_clean());
+    }
+
+    #[cfg(feature = "browser-cookies")]
+    #[tokio::test]
+    async fn test_browser_cookie_databases() {
+        use aes::cipher::{block_padding::Pkcs7, BlockEncryptMut, KeyIvInit};
+
+        use crate::{utils::cookie_pre::load_cookie_jar, ClientConfig, Cookie};
+
+        let config = ClientConfig::default();
+        let url = reqwest::Url::parse(&config.endpoints.www).unwrap();
+        let dir = std::env::temp_dir().join(format!("bing_client_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let firefox = dir.join("cookies.sqlite");
+        let connection = rusqlite::Connection::open(&firefox).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE moz_cookies (id INTEGER PRIMARY KEY, name TEXT, value TEXT, host TEXT, path TEXT, expiry INTEGER, isSecure INTEGER, isHttpOnly INTEGER, sameSite INTEGER);
+                INSERT INTO moz_cookies (name, value, host, path, expiry, isSecure, isHttpOnly, sameSite) VALUES
+                    ('_U', 'firefox-token', '.bing.com', '/', 4102444800000, 1, 1, 0),
+                    ('other', 'not-bing', '.example.com', '/', 0, 0, 0, 0);",
+            )
+            .unwrap();
+        drop(connection);
+        let (jar, _) = load_cookie_jar(
+            &Cookie::FirefoxDb(firefox.to_string_lossy().to_string()),
+            &config,
+        )
+        .await
+        .unwrap();
+        let cookies = jar.to_json_cookies();
+        assert!(cookies
+            .iter()
+            .any(|cookie| cookie.name == "_U" && cookie.expiration_date == Some(4102444800.0)));
+        assert!(!cookies.iter().any(|cookie| cookie.name == "other"));
+
+        let encrypt = |prefix: &[u8], password: &str, value: &[u8]| {
+            let key =
+                pbkdf2::pbkdf2_hmac_array::<sha1::Sha1, 16>(password.as_bytes(), b"saltysalt", 1);
+            let mut encrypted = prefix.to_vec();
+            encrypted.extend(
+                cbc::Encryptor::<aes::Aes128>::new(&key.into(), &[b' '; 16].into())
+                    .encrypt_padded_vec_mut::<Pkcs7>(value),
+            );
+            encrypted
+        };
+        // version 24 prefixes the value with the sha256 of the host
+        let mut hashed = vec![0u8; 32];
+        hashed.extend(b"chromium-token");
+        let chromium = dir.join("Cookies");
+        let connection = rusqlite::Connection::open(&chromium).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE meta (key TEXT, value TEXT);
+                INSERT INTO meta VALUES ('version', '24');
+                CREATE TABLE cookies (host_key TEXT, name TEXT, value TEXT, encrypted_value BLOB, path TEXT, expires_utc INTEGER, is_secure INTEGER, is_httponly INTEGER, samesite INTEGER);",
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO cookies VALUES ('.bing.com', '_U', '', ?1, '/', 17000000000000000, 1, 1, -1),
+                    ('www.bing.com', 'MUID', '', ?2, '/', 0, 0, 0, 1),
+                    ('.example.com', 'other', '', x'00', '/', 0, 0, 0, 0)",
+                rusqlite::params![
+                    encrypt(b"v10", "peanuts", &hashed),
+                    encrypt(b"v11", "secret", &[vec![0u8; 32], b"muid".to_vec()].concat()),
+                ],
+            )
+            .unwrap();
+        drop(connection);
+        let path = chromium.to_string_lossy().to_string();
+        assert!(load_cookie_jar(
+            &Cookie::ChromiumDb {
+                path: path.clone(),
+                password: None
+            },
+            &config
+        )
+        .await
+        .is_err());
+        let (jar, report) = load_cookie_jar(
+            &Cookie::ChromiumDb {
+                path,
+                password: Some("secret".to_string()),
+            },
+            &config,
+        )
+        .await
+        .unwrap();
+        let mut pairs: Vec<String> = jar
+            .cookie_header(&url)
+            .split("; ")
+            .map(str::to_string)
+            .collect();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            vec!["MUID=muid", "_EDGE_CD=m=en-us&u=en-us", "_U=chromium-token"]
+        );
+        assert!(report.is_valid());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    // A fake bing on localhost: the rest api answers "Success",
+    // the ChatHub sends `frames` after the user input and then waits for the client to close.
+    // A string frame is sent without quotes, "close" closes the connection instead.
+    // "drop" drops it without a close frame, "drop-next" once the next message arrives.
+    // A question on an open connection gets the same frames.
+    // Every signature fetch issues a new signature, requests with an older one get a 401.
+    async fn mock_bing(frames: Vec<serde_json::Value>) -> BingClient {
+        mock_bing_with(frames, true).await.0
+    }
+
+    // Like `mock_bing`, a stop invocation is confirmed with a type 3 frame if `ack_stop`.
+    // Returns the websocket messages received after the user input too.
+    async fn mock_bing_with(
+        frames: Vec<serde_json::Value>,
+        ack_stop: bool,
+    ) -> (BingClient, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio::{io::AsyncReadExt, net::TcpListener};
+        use tokio_tungstenite::tungstenite::Message::{self, Text};
+
+        let received = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let log = received.clone();
+        // only the latest signatures are accepted
+        let issued = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = server.accept().await.unwrap();
+                let frames = frames.clone();
+                let log = log.clone();
+                let issued = issued.clone();
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 4096];
+                    let n = socket.peek(&mut buf).await.unwrap();
+                    let head = String::from_utf8_lossy(&buf[..n]).to_string();
+                    let latest = issued.load(std::sync::atomic::Ordering::SeqCst);
+                    if head.contains("/sydney/ChatHub") {
+                        if !head.contains(&format!("sec_access_token=encrypted-{latest} ")) {
+                            socket
+                                .write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n")
+                                .await
+                                .unwrap();
+                            return;
+                        }
+                        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+                        ws.next().await;
+                        ws.send(Text("{}\x1e".to_string())).await.unwrap();
+                        // the echo and the user input
+                        ws.next().await;
+                        ws.next().await;
+                        let mut answer = true;
+                        while answer {
+                            answer = false;
+                            for frame in frames.clone() {
+                                let frame = match frame {
+                                    // closes the connection
+                                    serde_json::Value::String(raw) if raw == "close" => {
+                                        ws.close(None).await.unwrap();
+                                        return;
+                                    }
+                                    serde_json::Value::String(raw) if raw == "drop" => return,
+                                    serde_json::Value::String(raw) if raw == "drop-next" => {
+                                        ws.next().await;
+                                        return;
+                                    }
+                                    // sent as it is
+                                    serde_json::Value::String(raw) => raw,
+                                    frame => frame.to_string(),
+                                };
+                                ws.send(Text(format!("{frame}\x1e"))).await.unwrap();
+                            }
+                            while let Some(Ok(msg)) = ws.next().await {
+                                let msg = match msg {
+                                    Text(text) => text,
+                                    Message::Close(_) => "close".to_string(),
+                                    _ => continue,
+                                };
+                                // another question on the same connection is answered the same
+                                answer = msg.contains(r#""target":"chat""#);
+                                if ack_stop && msg.contains(r#""target":"stop""#) {
+                                    let stop: serde_json::Value =
+                                        serde_json::from_str(msg.trim_end_matches('\x1e')).unwrap();
+                                    let ack = serde_json::json!({
+                                        "type": 3,
+                                        "invocationId": stop["invocationId"],
+                                    });
+                                    ws.send(Text(format!("{ack}\x1e"))).await.unwrap();
+                                }
+                                log.lock().unwrap().push(msg);
+                                if answer {
+                                    break;
+                                }
+                            }
+                        }
+                        return;
+                    }
+                    let mut request = Vec::new();
+                    let body_len = loop {
+                        let n = socket.read(&mut buf).await.unwrap();
+                        request.extend_from_slice(&buf[..n]);
+                        let text = String::from_utf8_lossy(&request).to_string();
+                        if let Some(end) = text.find("\r\n\r\n") {
+                            let content_length = text
+                                .lines()
+                                .find_map(|line| {
+                                    line.to_lowercase()
+                                        .strip_prefix("content-length: ")
+                                        .map(|len| len.parse::<usize>().unwrap())
+                                })
+                                .unwrap_or(0);
+                            break end + 4 + content_length;
+                        }
+                    };
+                    while request.len() < body_len {
+                        let n = socket.read(&mut buf).await.unwrap();
+                        request.extend_from_slice(&buf[..n]);
+                    }
+                    let request = String::from_utf8_lossy(&request).to_string();
+                    let bearer = request.lines().find_map(|line| {
+                        line.to_lowercase()
+                            .strip_prefix("authorization: bearer ")
+                            .map(str::to_string)
+                    });
+                    if bearer.is_some_and(|bearer| bearer != format!("signature-{latest}")) {
+                        socket
+                            .write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n")
+                            .await
+                            .unwrap();
+                        return;
+                    }
+                    let headers = if request.contains("/turing/conversation/create") {
+                        let n = issued.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                        format!("X-Sydney-Conversationsignature: signature-{n}\r\nX-Sydney-Encryptedconversationsignature: encrypted-{n}\r\n")
+                    } else {
+                        String::new()
+                    };
+                    // a new conversation, a short history of it and a new name
+                    let body = r#"{"clientId":"client","conversationId":"new-conversation","chatName":"renamed","throttling":{"maxNumUserMessagesInConversation":30,"numUserMessagesInConversation":2},"messages":[
+                        {"author":"user","text":"first question","scores":[],"messageId":"u1","createdAt":"2024-04-01T10:00:00+00:00","tone":"Precise","privacy":"Internal"},
+                        {"author":"bot","text":"Searching the web for: `first`","messageType":"InternalSearchQuery","messageId":"s1","invocation":"search_web(query=\"first\")"},
+                        {"author":"bot","text":"first answer","suggestedResponses":[{"author":"user","text":"tell me more","messageType":"Suggestion","messageId":"r1"}],"messageId":"b1","createdAt":"2024-04-01T10:00:05+00:00","contentOrigin":"DeepLeo","offense":"None","feedback":{"tag":null,"updatedOn":null,"type":"None"}},
+                        {"author":"user","text":"second question","scores":[]},
+                        {"author":"bot","text":"second answer","suggestedResponses":[]}
+                    ],"result":{"value":"Success","message":null}}"#;
+                    socket
+                        .write_all(
+                            format!(
+                                "HTTP/1.1 200 OK\r\n{headers}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                                body.len()
+                            )
+                            .as_bytes(),
+                        )
+                        .await
+                        .unwrap();
+                });
+            }
+        });
+
+        let config = crate::ClientConfig {
+            endpoints: crate::Endpoints {
+                www: format!("http://{addr}"),
+                sydney: format!("http://{addr}"),
+                wss: format!("ws://{addr}"),
+            },
+            ..Default::default()
+        };
+        let client = BingClient::builder()
+            .config(config)
+            .build(&Cookie::HeadStr("_U=abc".to_string()))
+            .await
+            .unwrap();
+        (client, received)
+    }
+
+    fn mock_chat() -> crate::Chat {
+        serde_json::from_value(serde_json::json!({"conversationId": "conversation", "plugins": []}))
+            .unwrap()
+    }
+
+    // The question of the mock answers
+    async fn question(chat: &crate::Chat, client: &BingClient) -> UserInput {
+        UserInput::build(
+            "hi".to_string(),
+            None,
+            crate::Tone::Balanced,
+            vec![],
+            chat,
+            client,
+        )
+        .await
+        .unwrap()
+    }
+
+    // Asks `question` in `chat` on a new connection
+    async fn ask_owned(
+        client: BingClient,
+        chat: std::sync::Arc<crate::Chat>,
+    ) -> crate::AskStream {
+        let user_input = question(&chat, &client).await;
+        std::sync::Arc::new(client)
+            .ask_stream_owned(chat, user_input)
+            .await
+            .unwrap()
+    }
+
+    // Every item of an answer in a new mock chat
+    async fn collect_answer(
+        client: BingClient,
+    ) -> Vec<Result<crate::types::bot_easy_resp_type::BotResp, crate::BingError>> {
+        use futures_util::StreamExt;
+
+        ask_owned(client, std::sync::Arc::new(mock_chat()))
+            .await
+            .collect()
+            .await
+    }
+
+    // Like `collect_answer`, for answers without errors
+    async fn collect_resps(client: BingClient) -> Vec<crate::types::bot_easy_resp_type::BotResp> {
+        collect_answer(client)
+            .await
+            .into_iter()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    fn text_frame(text: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": 1,
+            "target": "update",
+            "arguments": [{"messages": [{"text": text, "author": "bot", "messageId": "m1"}]}]
+        })
+    }
+
+    fn final_frame() -> serde_json::Value {
+        serde_json::json!({
+            "type": 2,
+            "invocationId": "0",
+            "item": {
+                "messages": [{"author": "bot", "text": "Hello", "suggestedResponses": [{"text": "Hi"}]}],
+                "result": {"value": "Success"}
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_ask_stream_owned() {
+        use std::sync::Arc;
+
+        use futures_util::StreamExt;
+
+        use crate::types::bot_easy_resp_type::BotResp;
+
+        let client = mock_bing(vec![text_frame("Hel"), text_frame("Hello"), final_frame()]).await;
+        assert_eq!(client.client_id, "client");
+        let chat = Arc::new(mock_chat());
+        let stream = ask_owned(client, chat.clone()).await;
+        let handle = stream.stop_handle();
+        // the stream can be moved into a task
+        let resps = tokio::spawn(async move { stream.collect::<Vec<_>>().await })
+            .await
+            .unwrap();
+        let resps: Vec<BotResp> = resps.into_iter().map(Result::unwrap).collect();
+        assert!(matches!(&resps[..], [
+            BotResp::Text(a),
+            BotResp::Text(b),
+            BotResp::SuggestReply(suggests),
+        ] if a == "Hel" && b == "Hello" && suggests == &vec!["Hi".to_string()]));
+        assert!(!handle.is_stopped());
+        assert_eq!(
+            chat.x_sydney_encryptedconversationsignature
+                .read()
+                .await
+                .as_deref(),
+            Some("encrypted-1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_text_delta() {
+        use crate::types::{
+            bot_easy_resp_type::{BotResp, TextDelta},
+            client_config_type::TextMode,
+        };
+
+        let mut client = mock_bing(vec![
+            text_frame("Hel"),
+            text_frame("Hello"),
+            text_frame("Hello"),
+            // rewritten by bing
+            text_frame("Help 你好"),
+            text_frame("Help 你们"),
+            final_frame(),
+        ])
+        .await;
+        client.config.text_mode = TextMode::Delta;
+        let resps = collect_resps(client).await;
+        let deltas: Vec<TextDelta> = resps
+            .into_iter()
+            .filter_map(|resp| match resp {
+                BotResp::TextDelta(delta) => Some(delta),
+                BotResp::Text(_) => panic!("no cumulative text in delta mode"),
+                _ => None,
+            })
+            .collect();
+        let delta = |offset: usize, text: &str| TextDelta {
+            offset,
+            text: text.to_string(),
+        };
+        assert_eq!(
+            deltas,
+            vec![
+                delta(0, "Hel"),
+                delta(3, "lo"),
+                delta(3, "p 你好"),
+                delta(8, "们")
+            ]
+        );
+        let mut text = String::new();
+        for delta in &deltas {
+            delta.apply_to(&mut text);
+        }
+        assert_eq!(text, "Help 你们");
+        assert!(deltas[2].is_rewrite("Hello"));
+        assert!(!deltas[1].is_rewrite("Hel"));
+    }
+
+    #[tokio::test]
+    async fn test_ask_stream_plain() {
+        use crate::types::client_config_type::TextMode;
+
+        let mut final_with_sources = final_frame();
+        final_with_sources["item"]["messages"][0]["sourceAttributions"] = serde_json::json!([
+            {"providerDisplayName": "Rust", "seeMoreUrl": "https://www.rust-lang.org/"}
+        ]);
+        let mut client =
+            mock_bing(vec![text_frame("Hel"), text_frame("Hello"), final_with_sources]).await;
+        client.config.text_mode = TextMode::Delta;
+        let chat = mock_chat();
+        let (mut stream, _) = client
+            .ask_stream_plain(&chat, question(&chat, &client).await)
+            .await
+            .unwrap();
+        let mut texts = Vec::new();
+        while let GeneratorState::Yielded(text) = stream.async_resume().await {
+            texts.push(text);
+        }
+        // the text as it streams, then the answer rendered as markdown
+        assert_eq!(
+            texts,
+            vec![
+                "Hel".to_string(),
+                "Hello".to_string(),
+                "Hello\n\n**Sources**\n\n1. [Rust](https://www.rust-lang.org/)\n\n\
+                 **Suggest Replys**\n\n1. Hi"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stop_handle() {
+        use std::{sync::Arc, time::Duration};
+
+        use futures_util::StreamExt;
+
+        use crate::types::{bot_easy_resp_type::BotResp, stop_outcome_type::StopStatus};
+
+        let ask = |client| ask_owned(client, Arc::new(mock_chat()));
+
+        // the stream stalls after the first text, the stop is confirmed with a type 3 frame
+        let (client, received) = mock_bing_with(vec![text_frame("Hel")], true).await;
+        let mut stream = ask(client).await;
+        assert!(matches!(stream.next().await, Some(Ok(BotResp::Text(text))) if text == "Hel"));
+        let handle = stream.stop_handle();
+        let outcome = tokio::time::timeout(Duration::from_secs(5), handle.stop())
+            .await
+            .unwrap();
+        assert_eq!(outcome.status, StopStatus::Acknowledged);
+        assert_eq!(outcome.text, "Hel");
+        assert!(handle.is_stopped());
+        assert!(stream.next().await.is_none());
+        assert!(received.lock().unwrap()[0].contains(r#""target":"stop""#));
+        // after the end
+        assert_eq!(handle.stop().await.status, StopStatus::Finished);
+
+        // no confirmation
+        let (mut client, _) = mock_bing_with(vec![text_frame("Hel")], false).await;
+        client.config.stop_timeout = Duration::from_millis(200);
+        let mut stream = ask(client).await;
+        stream.next().await;
+        let outcome = stream.stop().await;
+        assert_eq!(outcome.status, StopStatus::TimedOut);
+        assert_eq!(outcome.text, "Hel");
+
+        // dropping the stream stops the answer and closes the websocket
+        let (client, received) = mock_bing_with(vec![text_frame("Hel")], false).await;
+        let mut stream = ask(client).await;
+        stream.next().await;
+        drop(stream);
+        for _ in 0..50 {
+            if received.lock().unwrap().iter().any(|msg| msg == "close") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        {
+            let received = received.lock().unwrap();
+            assert!(received[0].contains(r#""target":"stop""#));
+            assert_eq!(received[1], "close");
+        }
+
+        // in a session every stop has its own invocation id, an acknowledgement of another one is ignored
+        let (client, received) = mock_bing_with(
+            vec![
+                text_frame("Hel"),
+                serde_json::json!({"type": 3, "invocationId": "99"}),
+            ],
+            true,
+        )
+        .await;
+        let client = Arc::new(client);
+        let session =
+            crate::client::chat_session::ChatSession::new(client.clone(), Arc::new(mock_chat()))
+                .await
+                .unwrap();
+        for _ in 0..3 {
+            let mut stream = session
+                .ask_stream(question(&session.chat(), &client).await)
+                .await
+                .unwrap();
+            assert!(matches!(stream.next().await, Some(Ok(BotResp::Text(text))) if text == "Hel"));
+            let outcome = tokio::time::timeout(Duration::from_secs(5), stream.stop())
+                .await
+                .unwrap();
+            assert_eq!(outcome.status, StopStatus::Acknowledged);
+            assert!(stream.next().await.is_none());
+        }
+        let stops: Vec<String> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|msg| msg.contains(r#""target":"stop""#))
+            .cloned()
+            .collect();
+        assert_eq!(stops.len(), 3);
+        for (stop, id) in stops.iter().zip(["1", "3", "5"]) {
+            assert!(stop.contains(&format!(r#""invocationId":"{id}""#)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chat_timeouts() {
+        use std::time::Duration;
+
+        use crate::types::{
+            bot_easy_resp_type::BotResp,
+            chat_timeout_type::{ChatTimeouts, TimeoutPhase},
+            error_type::BingError,
+        };
+
+        let timed_answer = |frames, timeouts| async move {
+            let mut client = mock_bing(frames).await;
+            client.config.chat_timeouts = timeouts;
+            tokio::time::timeout(Duration::from_secs(5), collect_answer(client))
+                .await
+                .unwrap()
+        };
+        let short = Some(Duration::from_millis(200));
+        let no_timeouts = ChatTimeouts {
+            handshake: None,
+            first_token: None,
+            idle: None,
+            total: None,
+        };
+
+        let items = timed_answer(
+            vec![],
+            ChatTimeouts {
+                first_token: short,
+                ..no_timeouts
+            },
+        )
+        .await;
+        assert!(matches!(
+            &items[..],
+            [Err(BingError::ChatTimeout { phase: TimeoutPhase::FirstToken, partial_text })]
+                if partial_text.is_empty()
+        ));
+
+        let items = timed_answer(
+            vec![text_frame("Hel")],
+            ChatTimeouts {
+                idle: short,
+                ..no_timeouts
+            },
+        )
+        .await;
+        assert!(matches!(
+            &items[..],
+            [Ok(BotResp::Text(_)), Err(BingError::ChatTimeout { phase: TimeoutPhase::Idle, partial_text })]
+                if partial_text == "Hel"
+        ));
+
+        // pings keep the connection alive, but not longer than `total`
+        let items = timed_answer(
+            vec![text_frame("Hel"), serde_json::json!({"type": 6})],
+            ChatTimeouts {
+                total: short,
+                idle: Some(Duration::from_secs(5)),
+                ..no_timeouts
+            },
+        )
+        .await;
+        assert!(matches!(
+            items.last(),
+            Some(Err(BingError::ChatTimeout {
+                phase: TimeoutPhase::Total,
+                ..
+            }))
+        ));
+
+        // the answer finishes in time
+        let items = timed_answer(
+            vec![text_frame("Hello"), final_frame()],
+            ChatTimeouts::default(),
+        )
+        .await;
+        assert!(items.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn test_answer_errors() {
+        use crate::types::{bot_easy_resp_type::BotResp, error_type::BingError};
+
+        let throttled = serde_json::json!({
+            "type": 2,
+            "item": {
+                "messages": [],
+                "result": {"value": "Throttled", "message": "Request is throttled."}
+            }
+        });
+        let throttled_frame = throttled.clone();
+        let items = collect_answer(
+            mock_bing(vec![
+                text_frame("Hel"),
+                serde_json::json!("{not json"),
+                throttled,
+            ])
+            .await,
+        )
+        .await;
+        assert!(matches!(
+            &items[..],
+            [
+                Ok(BotResp::Text(_)),
+                Err(BingError::InvalidFrame { frame, .. }),
+                Err(BingError::Throttled { message: Some(message), .. }),
+            ] if frame == "{not json" && message == "Request is throttled."
+        ));
+
+        // a type 2 frame without an item
+        let items = collect_answer(mock_bing(vec![serde_json::json!({"type": 2})]).await).await;
+        assert!(matches!(&items[..], [Err(BingError::InvalidFrame { .. })]));
+
+        // cut off
+        let items =
+            collect_answer(mock_bing(vec![text_frame("Hel"), serde_json::json!("close")]).await)
+                .await;
+        assert!(matches!(
+            items.last(),
+            Some(Err(BingError::ChatClosed { partial_text, .. })) if partial_text == "Hel"
+        ));
+        let items = collect_answer(
+            mock_bing(vec![
+                serde_json::json!({"type": 7, "error": "Connection closed with an error.", "allowReconnect": true}),
+            ])
+            .await,
+        )
+        .await;
+        assert!(matches!(
+            &items[..],
+            [Err(BingError::ChatClosed { reason: Some(reason), .. })]
+                if reason == "Connection closed with an error."
+        ));
+
+        let items = collect_answer(mock_bing(vec![text_frame("Hello"), final_frame()]).await).await;
+        assert!(items.iter().all(Result::is_ok));
+
+        // the mock does not redirect the drawing, the failure is an error and not an apology
+        let draw_frame = serde_json::json!({
+            "type": 1,
+            "target": "update",
+            "arguments": [{"messages": [{
+                "text": "a cat",
+                "author": "bot",
+                "messageId": "d1",
+                "messageType": "GenerateContentQuery",
+                "contentType": "IMAGE"
+            }]}]
+        });
+        let frames = vec![text_frame("Hello"), draw_frame, final_frame()];
+        let items = collect_answer(mock_bing(frames.clone()).await).await;
+        assert!(matches!(
+            items.last(),
+            Some(Err(BingError::AnswerImage(e)))
+                if matches!(**e, BingError::MissingHeader { header: "Location", .. })
+        ));
+        assert!(!items
+            .iter()
+            .any(|item| matches!(item, Ok(BotResp::Apology(_)))));
+        let client = mock_bing(frames).await;
+        let chat = mock_chat();
+        let answer = client
+            .ask(&chat, question(&chat, &client).await)
+            .await
+            .unwrap();
+        assert_eq!(answer.text, "Hello");
+        assert!(answer.apologies.is_empty());
+        assert_eq!(answer.errors.len(), 1);
+
+        // the generator keeps the kind of the error
+        let client = mock_bing(vec![text_frame("Hel"), throttled_frame]).await;
+        let chat = mock_chat();
+        let (mut stream, _) = client
+            .ask_stream(&chat, question(&chat, &client).await)
+            .await
+            .unwrap();
+        let mut errors = Vec::new();
+        while let genawaiter::GeneratorState::Yielded(resp) = stream.async_resume().await {
+            if let BotResp::Error(error) = resp {
+                errors.push(error);
+            }
+        }
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, crate::ErrorKind::Throttled);
+        assert!(errors[0].message.contains("Request is throttled."));
+    }
+
+    #[tokio::test]
+    async fn test_search_resps() {
+        use crate::types::bot_easy_resp_type::{BotResp, WebResult};
+
+        let search_frame = serde_json::json!({
+            "type": 1,
+            "target": "update",
+            "arguments": [{"messages": [
+                {
+                    "text": "Searching the web for: `rust async`",
+                    "hiddenText": "rust async",
+                    "author": "bot",
+                    "messageType": "InternalSearchQuery",
+                    "messageId": "q1"
+                },
+                {
+                    "text": "",
+                    "author": "bot",
+                    "messageType": "InternalSearchResult",
+                    "messageId": "r1",
+                    "groundingInfo": {"web_search_results": [
+                        {"title": "Async Book", "url": "https://rust-lang.github.io/async-book/", "snippets": ["Async", "programming"]},
+                        {"title": "no url"}
+                    ]}
+                },
+                {
+                    "text": "Searching for: `tokio`",
+                    "author": "bot",
+                    "messageType": "InternalSearchQuery",
+                    "messageId": "q2"
+                },
+                {
+                    "text": "",
+                    "author": "bot",
+                    "messageType": "InternalSearchResult",
+                    "messageId": "r2",
+                    "hiddenText": "```json\n{\"web_search_results\":[{\"title\":\"Tokio\",\"url\":\"https://tokio.rs/\",\"snippets\":[\"A runtime\"]}]}\n```"
+                }
+            ]}]
+        });
+        let resps =
+            collect_resps(mock_bing(vec![search_frame, text_frame("Hello"), final_frame()]).await)
+                .await;
+        let result = |title: &str, url: &str, snippet: &str| WebResult {
+            title: title.to_string(),
+            url: url.to_string(),
+            snippet: snippet.to_string(),
+        };
+        assert!(matches!(
+            &resps[..4],
+            [
+                BotResp::SearchQuery(first),
+                BotResp::SearchResults(first_results),
+                BotResp::SearchQuery(second),
+                BotResp::SearchResults(second_results),
+            ] if first == "rust async"
+                && second == "tokio"
+                && first_results == &vec![result("Async Book", "https://rust-lang.github.io/async-book/", "Async programming")]
+                && second_results == &vec![result("Tokio", "https://tokio.rs/", "A runtime")]
+        ));
+        assert!(matches!(&resps[4], BotResp::Text(text) if text == "Hello"));
+    }
+
+    #[test]
+    fn test_citations() {
+        use crate::types::bot_easy_resp_type::{BotResp, SourceAttribution};
+        use crate::utils::process_bot_resp::json2bot_resp_type2;
+
+        let frame = serde_json::json!({
+            "type": 2,
+            "item": {
+                "messages": [{
+                    "author": "bot",
+                    "text": "Rust[^1^] is fast[^3^][^2^]. [^x^] [^9^]",
+                    "sourceAttributions": [
+                        {"providerDisplayName": "Rust", "seeMoreUrl": "https://www.rust-lang.org/"},
+                        {"providerDisplayName": "Search", "seeMoreUrl": "https://www.bing.com/search?q=rust"},
+                        {"providerDisplayName": 42},
+                    ]
+                }],
+                "result": {"value": "Success"}
+            }
+        });
+        let (resps, skipped) = json2bot_resp_type2(&frame).unwrap();
+        assert!(skipped.is_empty());
+        let [BotResp::SourceAttribution(sources), BotResp::Citations(cited)] = &resps[..] else {
+            panic!("{resps:?}");
+        };
+        // the bing search is left out of the list, the invalid entry too
+        assert_eq!(sources.len(), 1);
+        assert_eq!(cited.raw, "Rust[^1^] is fast[^3^][^2^]. [^x^] [^9^]");
+        assert_eq!(
+            cited.clean,
+            "Rust[1](https://www.rust-lang.org/) is fast[2](https://www.bing.com/search?q=rust). [^x^] "
+        );
+        let numbers: Vec<usize> = cited.citations.iter().map(|c| c.number).collect();
+        assert_eq!(numbers, vec![1, 3, 2, 9]);
+        for citation in &cited.citations {
+            assert_eq!(
+                &cited.raw[citation.raw_start..citation.raw_end],
+                format!("[^{}^]", citation.number)
+            );
+        }
+        let second = &cited.citations[2];
+        assert_eq!(
+            &cited.clean[second.clean_start..second.clean_end],
+            "[2](https://www.bing.com/search?q=rust)"
+        );
+        // the invalid entry keeps its place but has no url
+        assert_eq!(cited.citations[1].source, Some(SourceAttribution::default()));
+        assert_eq!(cited.citations[1].clean_start, cited.citations[1].clean_end);
+        assert_eq!(cited.citations[3].source, None);
+        let cited_sources: Vec<usize> = cited.cited_sources().iter().map(|(n, _)| *n).collect();
+        assert_eq!(cited_sources, vec![1, 3, 2]);
+
+        // a throttling that can not be parsed is skipped, the rest of the answer is kept
+        let mut frame = frame;
+        frame["item"]["throttling"] = serde_json::json!({"maxNumUserMessagesInConversation": "many"});
+        let (resps, skipped) = json2bot_resp_type2(&frame).unwrap();
+        assert!(matches!(
+            &resps[..],
+            [BotResp::SourceAttribution(_), BotResp::Citations(_)]
+        ));
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].starts_with("Failed to parse limit"));
+    }
+
+    #[tokio::test]
+    async fn test_message_types() {
+        use std::sync::Arc;
+
+        use futures_util::StreamExt;
+
+        use crate::types::{
+            bot_easy_resp_type::{BotResp, GeneratedCode},
+            chat_type::Chat,
+        };
+
+        let message = |message_type: &str, text: &str, extra: serde_json::Value| {
+            let mut message = serde_json::json!({
+                "text": text,
+                "author": "bot",
+                "messageType": message_type,
+                "messageId": message_type
+            });
+            message
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            message
+        };
+        let frame = serde_json::json!({
+            "type": 1,
+            "target": "update",
+            "arguments": [{"messages": [
+                message("Progress", "Analyzing the image", serde_json::json!({})),
+                message("InternalTasksMessage", "", serde_json::json!({"tasks": ["Search", {"text": "Write"}]})),
+                message("GeneratedCode", "```python\nprint(1)\n```", serde_json::json!({})),
+                message("GeneratedCode", "fn main() {}", serde_json::json!({"language": "rust"})),
+                message("RenderCardRequest", "card", serde_json::json!({})),
+                message("Disclaimer", "AI generated", serde_json::json!({})),
+                message("AdsQuery", "ads", serde_json::json!({})),
+                message("Disengaged", "Let's start over.", serde_json::json!({})),
+                message("RenderContentRequest", "content", serde_json::json!({})),
+                message("ActionRequest", "open", serde_json::json!({})),
+                message("ConfirmationCard", "sure?", serde_json::json!({})),
+                message("Context", "page", serde_json::json!({})),
+                message("SemanticSerp", "serp", serde_json::json!({})),
+                message("SearchQuery", "Searching for: `rust`", serde_json::json!({})),
+                message("GenerateContentQuery", "a song", serde_json::json!({"contentType": "SUNO"})),
+                message("Unknown", "new", serde_json::json!({})),
+                // no `messageId`, the image can't be drawn
+                serde_json::json!({"text": "a cat", "author": "bot", "messageType": "GenerateContentQuery", "contentType": "IMAGE"}),
+                serde_json::json!({"text": "a tip", "author": "bot", "messageType": "Progress"}),
+            ]}]
+        });
+        let client = mock_bing(vec![frame, final_frame()]).await;
+        let chat = Arc::new(mock_chat());
+        assert!(!chat.is_disengaged());
+        let resps: Vec<BotResp> = ask_owned(client, chat.clone())
+            .await
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        let code = |language: Option<&str>, code: &str| GeneratedCode {
+            language: language.map(str::to_string),
+            code: code.to_string(),
+        };
+        assert!(matches!(
+            &resps[..8],
+            [
+                BotResp::Progress(progress),
+                BotResp::Tasks(tasks),
+                BotResp::GeneratedCode(python),
+                BotResp::GeneratedCode(rust),
+                BotResp::RenderCard(card),
+                BotResp::Disclaimer(disclaimer),
+                BotResp::AdsQuery(ads),
+                BotResp::Disengaged(disengaged),
+            ] if progress == "Analyzing the image"
+                && tasks == &vec!["Search".to_string(), "Write".to_string()]
+                && python == &code(Some("python"), "print(1)")
+                && rust == &code(Some("rust"), "fn main() {}")
+                && card == "card"
+                && disengaged == "Let's start over."
+                && disclaimer == "AI generated"
+                && ads == "ads"
+        ));
+        assert!(matches!(
+            &resps[8..18],
+            [
+                BotResp::RenderContent(content),
+                BotResp::ActionRequest(action),
+                BotResp::ConfirmationCard(confirmation),
+                BotResp::Context(context),
+                BotResp::SemanticSerp(serp),
+                BotResp::SearchQuery(query),
+                BotResp::SongPrompt(song),
+                BotResp::Other { message_type: unknown, text: new },
+                BotResp::Other { message_type: image, text: cat },
+                BotResp::Progress(tip),
+            ] if content == "content"
+                && action == "open"
+                && confirmation == "sure?"
+                && context == "page"
+                && serp == "serp"
+                && query == "rust"
+                && song == "a song"
+                && unknown == "Unknown"
+                && new == "new"
+                && image == "GenerateContentQuery"
+                && cat == "a cat"
+                && tip == "a tip"
+        ));
+        assert!(chat.is_disengaged());
+        assert!(Chat::clone(&chat).await.is_disengaged());
+    }
+
+    #[tokio::test]
+    async fn test_withdrawn_answers() {
+        use crate::types::bot_easy_resp_type::{BotResp, Withdrawal};
+
+        let frame = |message: serde_json::Value| {
+            serde_json::json!({
+                "type": 1,
+                "target": "update",
+                "arguments": [{"messages": [message]}]
+            })
+        };
+
+        let apology = frame(serde_json::json!({
+            "text": "Sorry, let's talk about something else.",
+            "author": "bot",
+            "contentOrigin": "Apology",
+            "messageId": "m1"
+        }));
+        let resps = collect_resps(
+            mock_bing(vec![
+                text_frame("The secret is"),
+                apology.clone(),
+                apology,
+                final_frame(),
+            ])
+            .await,
+        )
+        .await;
+        assert!(matches!(
+            &resps[..3],
+            [BotResp::Text(_), BotResp::Retracted(withdrawal), BotResp::Apology(_)]
+                if withdrawal == &Withdrawal {
+                    text: "The secret is".to_string(),
+                    message_id: Some("m1".to_string()),
+                    content_origin: Some("Apology".to_string()),
+                    ..Default::default()
+                }
+        ));
+        // the second apology withdraws nothing
+        assert!(!resps[3..]
+            .iter()
+            .any(|resp| matches!(resp, BotResp::Retracted(_))));
+
+        // flagged only in the final frame
+        let moderated = serde_json::json!({
+            "type": 2,
+            "item": {
+                "messages": [{
+                    "author": "bot",
+                    "text": "",
+                    "offense": "Offensive",
+                    "contentOrigin": "DeepLeo",
+                    "hiddenText": "The secret is 42"
+                }],
+                "result": {"value": "Success"}
+            }
+        });
+        let resps = collect_resps(mock_bing(vec![text_frame("The secret is 42"), moderated]).await).await;
+        assert!(matches!(
+            &resps[..],
+            [BotResp::Text(_), BotResp::Moderated(withdrawal), ..]
+                if withdrawal.text == "The secret is 42"
+                    && withdrawal.offense.as_deref() == Some("Offensive")
+                    && withdrawal.hidden_text.as_deref() == Some("The secret is 42")
+        ));
+
+        // the text moved to `hiddenText` stays hidden while the replacement streams
+        let hidden = |text: &str| {
+            frame(serde_json::json!({
+                "text": text,
+                "author": "bot",
+                "hiddenText": "The secret is",
+                "messageId": "m1"
+            }))
+        };
+        let resps = collect_resps(
+            mock_bing(vec![
+                text_frame("The secret is"),
+                hidden("I can't"),
+                hidden("I can't say"),
+                final_frame(),
+            ])
+            .await,
+        )
+        .await;
+        let retracted: Vec<&Withdrawal> = resps
+            .iter()
+            .filter_map(|resp| match resp {
+                BotResp::Retracted(withdrawal) => Some(withdrawal),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(retracted.len(), 1);
+        assert_eq!(retracted[0].text, "The secret is");
+        assert!(matches!(&resps[3], BotResp::Text(text) if text == "I can't say"));
+
+        // flagged before any text was streamed
+        let flagged = frame(serde_json::json!({
+            "text": "",
+            "author": "bot",
+            "offense": "Offensive",
+            "messageId": "m1"
+        }));
+        let resps = collect_resps(mock_bing(vec![flagged, final_frame()]).await).await;
+        assert!(matches!(
+            &resps[0],
+            BotResp::Moderated(withdrawal)
+                if withdrawal.text.is_empty() && withdrawal.offense.as_deref() == Some("Offensive")
+        ));
+
+        // a normal answer
+        let resps = collect_resps(mock_bing(vec![text_frame("Hello"), final_frame()]).await).await;
+        assert!(!resps.iter().any(|resp| matches!(
+            resp,
+            BotResp::Retracted(_) | BotResp::Moderated(_)
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_session_state() {
+        use std::sync::Arc;
+
+        use futures_util::StreamExt;
+
+        let with_limit = |turns: u32| {
+            let mut frame = final_frame();
+            frame["item"]["throttling"] = serde_json::json!({
+                "maxNumUserMessagesInConversation": 30,
+                "numUserMessagesInConversation": turns,
+                "maxNumLongDocSummaryUserMessagesInConversation": 5,
+                "numLongDocSummaryUserMessagesInConversation": 0
+            });
+            frame
+        };
+        let client = Arc::new(mock_bing(vec![text_frame("Hello"), with_limit(3)]).await);
+        let chat = Arc::new(mock_chat());
+        assert!(chat.is_start_of_session());
+
+        let first = question(&chat, &client).await;
+        assert!(first.arguments[0].isStartOfSession);
+        let trace_id = first.arguments[0].traceId.clone();
+        let _: Vec<_> = client
+            .clone()
+            .ask_stream_owned(chat.clone(), first)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(chat.session().turns, 3);
+
+        let second = question(&chat, &client).await;
+        assert!(!second.arguments[0].isStartOfSession);
+        assert_eq!(second.arguments[0].traceId, trace_id);
+
+        // without a limit a finished answer counts as one turn
+        let client = Arc::new(mock_bing(vec![text_frame("Hello"), final_frame()]).await);
+        let _: Vec<_> = client
+            .ask_stream_owned(chat.clone(), second)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(chat.session().turns, 4);
+
+        // the session survives a round trip
+        let chat: crate::Chat = serde_json::from_str(&serde_json::to_string(&*chat).unwrap()).unwrap();
+        assert_eq!(chat.session().turns, 4);
+        assert_eq!(chat.session().trace_id, Some(trace_id));
+    }
+
+    #[tokio::test]
+    async fn test_rollover() {
+        use crate::types::{client_config_type::RolloverPolicy, user_input_type::Tone};
+
+        let mut client = mock_bing(vec![]).await;
+        let chat = mock_chat();
+        chat.set_turns(30);
+        chat.set_max_turns(30);
+        // off by default
+        assert!(client.rollover(&chat).await.unwrap().is_none());
+
+        client.config.rollover = RolloverPolicy::new_chat(2);
+        let fresh = mock_chat();
+        assert!(client.rollover(&fresh).await.unwrap().is_none());
+
+        let rollover = client.rollover(&chat).await.unwrap().unwrap();
+        assert_eq!(rollover.previous_conversation_id, "conversation");
+        assert_eq!(rollover.chat.conversation_id, "new-conversation");
+        assert_eq!(
+            rollover.summary,
+            "Continue our previous conversation, which ended with:\nuser: second question\nbot: second answer"
+        );
+        let user_input = UserInput::build(
+            "third question".to_string(),
+            None,
+            Tone::Balanced,
+            vec![],
+            &rollover.chat,
+            &client,
+        )
+        .await
+        .unwrap();
+        assert!(user_input.arguments[0].isStartOfSession);
+        assert_eq!(
+            user_input.arguments[0].message.text,
+            format!("{}\n\nthird question", rollover.summary)
+        );
+        // the summary is sent until bing answers a question
+        let user_input = UserInput::build(
+            "fourth question".to_string(),
+            None,
+            Tone::Balanced,
+            vec![],
+            &rollover.chat,
+            &client,
+        )
+        .await
+        .unwrap();
+        assert!(user_input.arguments[0].message.text.starts_with(&rollover.summary));
+    }
+
+    #[tokio::test]
+    async fn test_auto_rollover() {
+        use std::sync::Arc;
+
+        use futures_util::StreamExt;
+
+        use crate::{
+            client::chat_session::ChatSession,
+            types::{bot_easy_resp_type::BotResp, client_config_type::RolloverPolicy},
+        };
+
+        let full_chat = || {
+            let chat = mock_chat();
+            chat.set_turns(30);
+            chat.set_max_turns(30);
+            chat
+        };
+        let (mut client, received) =
+            mock_bing_with(vec![text_frame("Hello"), final_frame()], false).await;
+        client.config.rollover = RolloverPolicy::new_chat(2);
+
+        // `ask` moves the question to a new chat and says so
+        let chat = full_chat();
+        let answer = client
+            .ask(&chat, question(&chat, &client).await)
+            .await
+            .unwrap();
+        assert_eq!(answer.text, "Hello");
+        let rollover = answer.rollover.unwrap();
+        assert_eq!(rollover.previous_conversation_id, "conversation");
+        assert_eq!(rollover.chat.conversation_id, "new-conversation");
+        // bing answered, so the summary is not sent again
+        assert_eq!(rollover.chat.session().seed, None);
+        assert_eq!(rollover.chat.session().turns, 1);
+
+        // a stream starts with the rollover
+        let client = Arc::new(client);
+        let resps: Vec<BotResp> = client
+            .clone()
+            .ask_stream_owned(Arc::new(full_chat()), question(&full_chat(), &client).await)
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert!(matches!(
+            &resps[..2],
+            [BotResp::Rollover(rollover), BotResp::Text(text)]
+                if rollover.chat.conversation_id == "new-conversation" && text == "Hello"
+        ));
+
+        // the session continues in the new chat, on a new connection
+        let session = ChatSession::new(client.clone(), Arc::new(mock_chat()))
+            .await
+            .unwrap();
+        session.chat().set_turns(30);
+        session.chat().set_max_turns(30);
+        let answer = session
+            .ask(question(&session.chat(), &client).await)
+            .await
+            .unwrap();
+        assert!(answer.rollover.is_some());
+        assert_eq!(session.chat().conversation_id, "new-conversation");
+        let answer = session
+            .ask(question(&session.chat(), &client).await)
+            .await
+            .unwrap();
+        assert!(answer.rollover.is_none());
+        let invocations: Vec<String> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|msg| msg.contains(r#""target":"chat""#))
+            .cloned()
+            .collect();
+        assert_eq!(invocations.len(), 1);
+        assert!(invocations[0].contains(r#""conversationId":"new-conversation""#));
+        assert!(!invocations[0].contains("Continue our previous conversation"));
+    }
+
+    #[tokio::test]
+    async fn test_ask() {
+        use crate::types::{
+            bot_answer_type::{BotAnswer, FinishStatus},
+            error_type::BingError,
+        };
+
+        let mut final_with_sources = final_frame();
+        final_with_sources["item"]["messages"][0]["sourceAttributions"] = serde_json::json!([
+            {"providerDisplayName": "Rust", "seeMoreUrl": "https://www.rust-lang.org/"}
+        ]);
+        final_with_sources["item"]["messages"][0]["text"] = serde_json::json!("Hello[^1^]");
+        final_with_sources["item"]["throttling"] = serde_json::json!({
+            "maxNumUserMessagesInConversation": 30,
+            "numUserMessagesInConversation": 1,
+            "maxNumLongDocSummaryUserMessagesInConversation": 5,
+            "numLongDocSummaryUserMessagesInConversation": 0
+        });
+        let client = mock_bing(vec![
+            text_frame("Hel"),
+            serde_json::json!("{broken"),
+            text_frame("Hello[^1^]"),
+            final_with_sources,
+        ])
+        .await;
+        let chat = mock_chat();
+        let answer = client
+            .ask(&chat, question(&chat, &client).await)
+            .await
+            .unwrap();
+        assert_eq!(answer.text, "Hello[^1^]");
+        assert_eq!(answer.suggested_replies, vec!["Hi"]);
+        assert_eq!(answer.sources.len(), 1);
+        assert_eq!(
+            answer.citations.as_ref().unwrap().clean,
+            "Hello[1](https://www.rust-lang.org/)"
+        );
+        assert_eq!(answer.limit.as_ref().unwrap().num_user_messages, 1);
+        assert_eq!(answer.finish, FinishStatus::Completed);
+        let stored: BotAnswer =
+            serde_json::from_str(&serde_json::to_string(&answer).unwrap()).unwrap();
+        assert_eq!(stored.text, answer.text);
+        assert_eq!(stored.limit, answer.limit);
+
+        let throttled = serde_json::json!({
+            "type": 2,
+            "item": {"messages": [], "result": {"value": "Throttled"}}
+        });
+        let client = mock_bing(vec![text_frame("Hel"), throttled]).await;
+        let error = client
+            .ask(&chat, question(&chat, &client).await)
+            .await
+            .unwrap_err();
+        assert!(matches!(error.cause(), BingError::Throttled { .. }));
+        let answer = error.partial_answer().unwrap();
+        assert_eq!(answer.text, "Hel");
+        assert_eq!(answer.finish, FinishStatus::Error);
+
+        // stalled after the first text
+        let mut client = mock_bing(vec![text_frame("Hel")]).await;
+        client.config.chat_timeouts.idle = Some(std::time::Duration::from_millis(200));
+        let error = client
+            .ask(&chat, question(&chat, &client).await)
+            .await
+            .unwrap_err();
+        assert!(matches!(error.cause(), BingError::ChatTimeout { .. }));
+        let answer = error.partial_answer().unwrap();
+        assert_eq!(answer.text, "Hel");
+        assert_eq!(answer.finish, FinishStatus::TimedOut);
+
+        // stopped
+        let client = mock_bing(vec![text_frame("Hel")]).await;
+        let mut stream = ask_owned(client, std::sync::Arc::new(mock_chat())).await;
+        futures_util::StreamExt::next(&mut stream).await;
+        assert_eq!(stream.stop().await.text, "Hel");
+        let answer = stream.answer().await.unwrap();
+        assert_eq!(answer.finish, FinishStatus::Stopped);
+
+        // the searches, tasks and code of the answer are kept
+        let frame = serde_json::json!({
+            "type": 1,
+            "target": "update",
+            "arguments": [{"messages": [
+                {"text": "Searching the web for: `rust`", "author": "bot", "messageType": "InternalSearchQuery", "messageId": "q"},
+                {"text": "", "author": "bot", "messageType": "InternalSearchResult", "messageId": "r",
+                    "groundingInfo": {"web_search_results": [{"title": "Rust", "url": "https://www.rust-lang.org/", "snippets": ["fast"]}]}},
+                {"text": "Search\nWrite", "author": "bot", "messageType": "InternalTasksMessage", "messageId": "t"},
+                {"text": "fn main() {}", "author": "bot", "messageType": "GeneratedCode", "messageId": "c", "language": "rust"},
+            ]}]
+        });
+        let client = mock_bing(vec![frame, text_frame("Hello"), final_frame()]).await;
+        let answer = client
+            .ask(&chat, question(&chat, &client).await)
+            .await
+            .unwrap();
+        assert_eq!(answer.search_queries, vec!["rust"]);
+        assert_eq!(answer.search_results[0].url, "https://www.rust-lang.org/");
+        assert_eq!(answer.tasks, vec!["Search", "Write"]);
+        assert_eq!(answer.generated_code[0].code, "fn main() {}");
+        assert_eq!(answer.finish, FinishStatus::Completed);
+    }
+
+    #[test]
+    fn test_answer_renderers() {
+        use crate::types::{
+            bot_answer_type::BotAnswer,
+            bot_easy_resp_type::{Image, SourceAttribution},
+            render_type::{LinkStyle, MarkdownFlavor, RenderOptions, Section},
+        };
+        use crate::utils::{
+            answer_render::{
+                AnswerRenderer, HtmlRenderer, JsonRenderer, MarkdownRenderer, PlainRenderer,
+            },
+            citation::cite,
+        };
+
+        let sources = vec![SourceAttribution {
+            display_name: Some("Rust".to_string()),
+            see_more_url: Some("https://www.rust-lang.org/".to_string()),
+            image: None,
+        }];
+        let text = "Rust[^1^] is <fast>".to_string();
+        let answer = BotAnswer {
+            citations: Some(cite(&text, &sources)),
+            text,
+            sources,
+            images: vec![Image {
+                name: "cat.jpg".to_string(),
+                url: "https://example.com/cat.jpg".to_string(),
+            }],
+            suggested_replies: vec!["Why?".to_string(), "How?".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            MarkdownRenderer::default().render(&answer),
+            "Rust[1](https://www.rust-lang.org/) is <fast>\n\n\
+             **Images**\n\n1. ![cat.jpg](https://example.com/cat.jpg)\n\n\
+             **Sources**\n\n1. [Rust](https://www.rust-lang.org/)\n\n\
+             **Suggest Replys**\n\n1. Why?\n2. How?"
+        );
+
+        let options = RenderOptions {
+            sections: vec![Section::Suggestions, Section::Text],
+            first_number: 0,
+            ..Default::default()
+        };
+        let slack = MarkdownRenderer {
+            options: options.clone(),
+            flavor: MarkdownFlavor::Slack,
+        };
+        assert_eq!(
+            slack.render(&answer),
+            "*Suggest Replys*\n\n0. Why?\n1. How?\n\nRust<https://www.rust-lang.org/|1> is &lt;fast&gt;"
+        );
+        // the limit is numbered like the other lists
+        let limited = BotAnswer {
+            limit: Some(crate::types::bot_easy_resp_type::Limit {
+                max_num_user_messages: 30,
+                num_user_messages: 2,
+                max_num_long_doc_summary_user_messages: 5,
+                num_long_doc_summary_user_messages: 0,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            MarkdownRenderer::default().render(&limited),
+            "**Limits**\n\n1. 2 of 30"
+        );
+
+        let html = HtmlRenderer {
+            options: RenderOptions {
+                sections: vec![Section::Text, Section::Sources],
+                ..Default::default()
+            },
+        };
+        assert_eq!(
+            html.render(&answer),
+            "Rust<a href=\"https://www.rust-lang.org/\">1</a> is &lt;fast&gt;\n\n\
+             <b>Sources</b>\n\n1. <a href=\"https://www.rust-lang.org/\">Rust</a>"
+        );
+
+        let mut plain = PlainRenderer {
+            options: RenderOptions {
+                sections: vec![Section::Text, Section::Sources],
+                citations: false,
+                ..Default::default()
+            },
+        };
+        plain.options.headings.sources = String::new();
+        assert_eq!(
+            plain.render(&answer),
+            "Rust[^1^] is <fast>\n\n1. Rust (https://www.rust-lang.org/)"
+        );
+        plain.options.link_style = LinkStyle::Bare;
+        plain.options.citations = true;
+        assert_eq!(
+            plain.render(&answer),
+            "Rusthttps://www.rust-lang.org/ is <fast>\n\n1. https://www.rust-lang.org/"
+        );
+
+        let json = JsonRenderer {
+            options: RenderOptions {
+                sections: vec![Section::Suggestions],
+                ..Default::default()
+            },
+            pretty: false,
+        };
+        assert_eq!(
+            json.render(&answer),
+            r#"{"finish":"Completed","suggested_replies":["Why?","How?"]}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chat_session() {
+        use std::{sync::Arc, time::Duration};
+
+        use futures_util::StreamExt;
+
+        use crate::client::chat_session::ChatSession;
+
+        let (mut client, received) =
+            mock_bing_with(vec![text_frame("Hello"), final_frame()], false).await;
+        client.config.heartbeat_interval = Duration::from_millis(50);
+        let client = Arc::new(client);
+        let session = ChatSession::new(client.clone(), Arc::new(mock_chat()))
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            let answer = session.ask(question(&session.chat(), &client).await).await.unwrap();
+            assert_eq!(answer.text, "Hello");
+        }
+        // the later questions came on the first connection, every other invocation id is a stop
+        let invocations: Vec<String> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|msg| msg.contains(r#""target":"chat""#))
+            .cloned()
+            .collect();
+        assert_eq!(invocations.len(), 2);
+        assert!(invocations[0].contains(r#""invocationId":"2""#));
+        assert!(invocations[1].contains(r#""invocationId":"4""#));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(received
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|msg| msg.trim_end_matches('\x1e') == r#"{"type":6}"#));
+        drop(session);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(received.lock().unwrap().last().unwrap(), "close");
+
+        // bing closes the connection after every answer
+        let client = Arc::new(
+            mock_bing(vec![
+                text_frame("Hello"),
+                final_frame(),
+                serde_json::json!("close"),
+            ])
+            .await,
+        );
+        let session = ChatSession::new(client.clone(), Arc::new(mock_chat()))
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            let resps: Vec<_> = session
+                .ask_stream(question(&session.chat(), &client).await)
+                .await
+                .unwrap()
+                .collect()
+                .await;
+            assert!(resps.iter().all(Result::is_ok));
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        // a ping of bing between the answers is answered
+        let (client, received) = mock_bing_with(
+            vec![
+                text_frame("Hello"),
+                final_frame(),
+                serde_json::json!({"type": 6}),
+            ],
+            false,
+        )
+        .await;
+        let client = Arc::new(client);
+        let session = ChatSession::new(client.clone(), Arc::new(mock_chat()))
+            .await
+            .unwrap();
+        session.ask(question(&session.chat(), &client).await).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(received
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|msg| msg.trim_end_matches('\x1e') == r#"{"type":6}"#));
+
+        // bing drops the connection between the answers without a close frame
+        let client = Arc::new(
+            mock_bing(vec![
+                text_frame("Hello"),
+                final_frame(),
+                serde_json::json!("drop"),
+            ])
+            .await,
+        );
+        let session = ChatSession::new(client.clone(), Arc::new(mock_chat()))
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            let answer = session.ask(question(&session.chat(), &client).await).await.unwrap();
+            assert_eq!(answer.text, "Hello");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        // bing drops the connection as the next question arrives, it is sent again on a new one
+        let (client, received) = mock_bing_with(
+            vec![text_frame("Hello"), final_frame(), serde_json::json!("drop-next")],
+            false,
+        )
+        .await;
+        let client = Arc::new(client);
+        let session = ChatSession::new(client.clone(), Arc::new(mock_chat()))
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            let answer = session.ask(question(&session.chat(), &client).await).await.unwrap();
+            assert_eq!(answer.text, "Hello");
+        }
+        // the dropped questions are not logged
+        let invocations: Vec<String> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|msg| msg.contains(r#""target":"chat""#))
+            .cloned()
+            .collect();
+        assert!(invocations.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_signature_cache() {
+        use std::{sync::Arc, time::Duration};
+
+        use crate::types::chat_type::Chat;
+
+        async fn signature(chat: &Chat) -> String {
+            chat.x_sydney_conversationsignature
+                .read()
+                .await
+                .clone()
+                .unwrap()
+        }
+
+        let mut client = mock_bing(vec![text_frame("Hello"), final_frame()]).await;
+        let chat = mock_chat();
+        client.get_chat_messages(&chat).await.unwrap();
+        client.get_chat_messages(&chat).await.unwrap();
+        client
+            .rename_chat(&chat, "renamed".to_string())
+            .await
+            .unwrap();
+        assert_eq!(signature(&chat).await, "signature-1");
+
+        // another chat gets a newer signature, the cached one is rejected once
+        let other = mock_chat();
+        client.get_chat_messages(&other).await.unwrap();
+        client
+            .rename_chat(&chat, "renamed".to_string())
+            .await
+            .unwrap();
+        assert_eq!(signature(&chat).await, "signature-3");
+
+        // the same for the ChatHub handshake
+        client.get_chat_messages(&other).await.unwrap();
+        let answer = client
+            .ask(&chat, question(&chat, &client).await)
+            .await
+            .unwrap();
+        assert_eq!(answer.text, "Hello");
+        assert_eq!(signature(&chat).await, "signature-5");
+
+        // expired
+        client.config.signature_ttl = Duration::ZERO;
+        client.get_chat_messages(&chat).await.unwrap();
+        assert_eq!(signature(&chat).await, "signature-6");
+
+        // a session reconnects with a new signature too
+        client.config.signature_ttl = Duration::from_secs(60);
+        client.get_chat_messages(&other).await.unwrap();
+        let client = Arc::new(client);
+        let chat = Arc::new(chat);
+        assert!(crate::ChatSession::new(client.clone(), chat.clone())
+            .await
+            .is_ok());
+        assert_eq!(signature(&chat).await, "signature-8");
+    }
+
+    #[tokio::test]
+    async fn test_conversation() {
+        let client = mock_bing(vec![]).await;
+        let chat = mock_chat();
+        let conversation = client.get_conversation(&chat).await.unwrap();
+        assert_eq!(conversation.conversation_id, "new-conversation");
+        assert_eq!(conversation.chat_name.as_deref(), Some("renamed"));
+        assert_eq!(conversation.result.as_ref().unwrap().value, "Success");
+        assert_eq!(conversation.messages.len(), 5);
+        assert_eq!(conversation.turns(), 2);
+        assert_eq!(chat.session().turns, 2);
+        assert_eq!(conversation.max_turns(), Some(30));
+        assert_eq!(chat.session().max_turns, Some(30));
+
+        let question = &conversation.messages[0];
+        assert!(question.is_from_user());
+        assert_eq!(question.message_id.as_deref(), Some("u1"));
+        assert_eq!(
+            question.created_at.as_deref(),
+            Some("2024-04-01T10:00:00+00:00")
+        );
+        assert!(matches!(question.tone(), Some(crate::Tone::Precise)));
+        assert_eq!(question.extra["privacy"], "Internal");
+
+        let search = &conversation.messages[1];
+        assert_eq!(search.message_type.as_deref(), Some("InternalSearchQuery"));
+        assert_eq!(search.invocation.as_deref(), Some("search_web(query=\"first\")"));
+        assert!(!search.is_shown());
+
+        let answer = &conversation.messages[2];
+        assert_eq!(answer.content_origin.as_deref(), Some("DeepLeo"));
+        assert_eq!(answer.offense.as_deref(), Some("None"));
+        assert_eq!(
+            answer.feedback.as_ref().unwrap().feedback_type.as_deref(),
+            Some("None")
+        );
+        assert_eq!(answer.suggested_replies(), vec!["tell me more"]);
+        assert_eq!(
+            answer.suggested_responses.as_ref().unwrap()[0]
+                .message_type
+                .as_deref(),
+            Some("Suggestion")
+        );
+
+        // a field of another type does not fail the message, any adaptiveCards but null shows it
+        let msg: crate::ChatMessage = serde_json::from_value(serde_json::json!({
+            "author": "bot",
+            "text": "a card",
+            "contentType": "TEXT",
+            "adaptiveCards": [],
+            "invocation": {"name": "search_web"},
+            "feedback": "None",
+        }))
+        .unwrap();
+        assert_eq!(msg.invocation, None);
+        assert_eq!(msg.feedback, None);
+        assert!(msg.is_shown());
+        let msg: crate::ChatMessage = serde_json::from_value(serde_json::json!({
+            "author": "bot",
+            "text": "no card",
+            "contentType": "TEXT",
+            "adaptiveCards": null,
+        }))
+        .unwrap();
+        assert!(!msg.is_shown());
+
+        // round trips with the fields it does not know
+        let json = serde_json::to_value(&conversation).unwrap();
+        assert_eq!(json["messages"][0]["privacy"], "Internal");
+        assert_eq!(
+            serde_json::from_value::<crate::Conversation>(json).unwrap(),
+            conversation
+        );
+
+        // the easy messages are the shown ones
+        let msgs = client.get_chat_messages(&chat).await.unwrap();
+        let texts: Vec<_> = msgs.iter().map(|msg| msg.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["first question", "first answer", "second question", "second answer"]
+        );
+        assert_eq!(msgs[1].suggest_replys, vec!["tell me more"]);
+    }
+
+    #[tokio::test]
+    async fn test_history_images() {
+        use std::{
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            },
+            time::Duration,
+        };
+
+        use tokio::{io::AsyncReadExt, net::TcpListener};
+
+        use crate::{types::conversation_type::ChatMessage, EasyMsg};
+
+        // serves one image per url after a while, "missing" is not found
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        {
+            let in_flight = in_flight.clone();
+            let most = most.clone();
+            tokio::spawn(async move {
+                loop {
+                    let (mut socket, _) = server.accept().await.unwrap();
+                    let in_flight = in_flight.clone();
+                    let most = most.clone();
+                    tokio::spawn(async move {
+                        let mut buf = vec![0u8; 4096];
+                        let n = socket.read(&mut buf).await.unwrap();
+                        let req = String::from_utf8_lossy(&buf[..n]).to_string();
+                        let path = req.split_whitespace().nth(1).unwrap().to_string();
+                        let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        most.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        let response = if path.contains("missing") {
+                            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                                .to_string()
+                        } else {
+                            let body = format!(
+                                r#"<img src="https://th.bing.com/th/id/{}?w=270">"#,
+                                path.rsplit('/').next().unwrap()
+                            );
+                            format!(
+                                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                                body.len()
+                            )
+                        };
+                        socket.write_all(response.as_bytes()).await.unwrap();
+                    });
+                }
+            });
+        }
+
+        let drawing = |ids: &[&str]| {
+            let body: Vec<_> = ids
+                .iter()
+                .map(|id| serde_json::json!({"persistentUrl": format!("http://{addr}/images/{id}")}))
+                .collect();
+            serde_json::from_value::<ChatMessage>(serde_json::json!({
+                "author": "bot",
+                "text": "drawn",
+                "contentType": "IMAGE",
+                "adaptiveCards": [{"body": body}]
+            }))
+            .unwrap()
+        };
+        let question = serde_json::from_value::<ChatMessage>(
+            serde_json::json!({"author": "user", "text": "draw", "scores": []}),
+        )
+        .unwrap();
+
+        // the references are there without loading anything
+        let mut msgs: Vec<EasyMsg> = [drawing(&["a", "b"]), question, drawing(&["missing", "c", "d"])]
+            .iter()
+            .map(EasyMsg::from)
+            .collect();
+        assert_eq!(
+            msgs[0].pending_images,
+            vec![format!("http://{addr}/images/a"), format!("http://{addr}/images/b")]
+        );
+        assert!(msgs[1].pending_images.is_empty());
+        assert_eq!(most.load(Ordering::SeqCst), 0);
+
+        let mut client = mock_bing(vec![]).await;
+        client.config.image_concurrency = 2;
+        client.resolve_images(&mut msgs).await;
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+
+        // a failed image stays with its message
+        assert_eq!(msgs.len(), 3);
+        let urls = |msg: &EasyMsg| -> Vec<String> {
+            msg.images.iter().map(|image| image.url.clone()).collect()
+        };
+        assert_eq!(
+            urls(&msgs[0]),
+            vec!["https://th.bing.com/th/id/a", "https://th.bing.com/th/id/b"]
+        );
+        assert_eq!(
+            urls(&msgs[2]),
+            vec!["https://th.bing.com/th/id/c", "https://th.bing.com/th/id/d"]
+        );
+        assert_eq!(msgs[2].image_errors.len(), 1);
+        assert_eq!(msgs[2].image_errors[0].url, format!("http://{addr}/images/missing"));
+        assert!(msgs.iter().all(|msg| msg.pending_images.is_empty()));
+        assert_eq!(msgs[2].text, "drawn");
+
+        // the history loads without its images
+        let chat = mock_chat();
+        let msgs = client.get_chat_messages(&chat).await.unwrap();
+        assert_eq!(msgs.len(), 4);
+    }
 }
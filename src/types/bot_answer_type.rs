@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bot_easy_resp_type::{BotResp, GeneratedCode, Image, Limit, SourceAttribution, WebResult},
+    chat_msg_type::EasyMsg,
+    citation_type::CitedText,
+    error_type::BingError,
+    rollover_type::ChatRollover,
+};
+
+// How an answer ended
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FinishStatus {
+    #[default]
+    Completed,
+    // Bing ended the conversation, see `Chat::is_disengaged`
+    Disengaged,
+    // The streamed answer was taken back, `BotAnswer.text` is only what was streamed after it,
+    // an apology that replaced it is in `apologies`
+    Retracted,
+    // The streamed answer was flagged by bing's content filter
+    Moderated,
+    // The answer was stopped, see `AskStream::stop`
+    Stopped,
+    // See `ChatTimeouts`
+    TimedOut,
+    // The answer ended with an error, see `BingError::PartialAnswer`
+    Error,
+}
+
+// A whole answer, returned by `BingClient::ask`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BotAnswer {
+    pub text: String,
+    pub images: Vec<Image>,
+    pub sources: Vec<SourceAttribution>,
+    // The final text with its `[^n^]` markers resolved
+    pub citations: Option<CitedText>,
+    pub suggested_replies: Vec<String>,
+    pub limit: Option<Limit>,
+    // The web searches bing ran for the answer and what they found
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub search_queries: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub search_results: Vec<WebResult>,
+    // The steps of `InternalTasksMessage`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tasks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub generated_code: Vec<GeneratedCode>,
+    pub notices: Vec<String>,
+    pub apologies: Vec<String>,
+    // Errors that did not end the answer, e.g. a drawing that failed
+    pub errors: Vec<String>,
+    pub finish: FinishStatus,
+    // The question went to a new chat, ask the next ones there
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollover: Option<ChatRollover>,
+}
+
+impl BotAnswer {
+    // Adds a response of the stream, e.g. to build an answer from an `AskStream`
+    pub fn push(&mut self, resp: BotResp) {
+        match resp {
+            BotResp::Text(text) => self.text = text,
+            BotResp::TextDelta(delta) => delta.apply_to(&mut self.text),
+            BotResp::SuggestReply(mut suggests) => self.suggested_replies.append(&mut suggests),
+            BotResp::Notice(notice) => self.notices.push(notice),
+            BotResp::Image(mut images) => self.images.append(&mut images),
+            BotResp::Apology(apology) => self.apologies.push(apology),
+            BotResp::Error(error) => self.errors.push(error.message),
+            BotResp::Rollover(rollover) => self.rollover = Some(rollover),
+            BotResp::SourceAttribution(mut sources) => self.sources.append(&mut sources),
+            BotResp::Citations(cited) => self.citations = Some(cited),
+            BotResp::Limit(limit) => self.limit = Some(limit),
+            BotResp::Disengaged(_) => self.finish = FinishStatus::Disengaged,
+            BotResp::Retracted(_) => {
+                self.text.clear();
+                self.finish = FinishStatus::Retracted;
+            }
+            BotResp::Moderated(_) => {
+                self.text.clear();
+                self.finish = FinishStatus::Moderated;
+            }
+            BotResp::SearchQuery(query) => self.search_queries.push(query),
+            BotResp::SearchResults(mut results) => self.search_results.append(&mut results),
+            BotResp::Tasks(mut tasks) => self.tasks.append(&mut tasks),
+            BotResp::GeneratedCode(code) => self.generated_code.push(code),
+            // `TextDelta` is in `text` already, the others only matter while the answer streams,
+            // e.g. `Progress`, or are for a client that acts on them, e.g. `ActionRequest`
+            _ => {}
+        }
+    }
+
+    // Adds an item of an answer stream. A broken frame is skipped, a failed image goes to `errors`,
+    // any other error ends the answer and is kept in `error`.
+    pub(crate) fn push_item(
+        &mut self,
+        item: Result<BotResp, BingError>,
+        error: &mut Option<BingError>,
+    ) {
+        match item {
+            Ok(resp) => self.push(resp),
+            Err(BingError::InvalidFrame { .. }) => {}
+            Err(e @ BingError::AnswerImage(_)) => self.errors.push(e.to_string()),
+            Err(e) => *error = Some(e),
+        }
+    }
+
+    // The answer, or `error` with the answer so far
+    pub(crate) fn finish(
+        mut self,
+        error: Option<BingError>,
+        stopped: bool,
+    ) -> Result<BotAnswer, BingError> {
+        if let Some(error) = error {
+            self.finish = match error {
+                BingError::ChatTimeout { .. } => FinishStatus::TimedOut,
+                _ => FinishStatus::Error,
+            };
+            return Err(BingError::PartialAnswer {
+                answer: Box::new(self),
+                error: Box::new(error),
+            });
+        }
+        if stopped && self.finish == FinishStatus::Completed {
+            self.finish = FinishStatus::Stopped;
+        }
+        Ok(self)
+    }
+}
+
+// A message of the history, so it can be rendered like an answer
+impl From<EasyMsg> for BotAnswer {
+    fn from(msg: EasyMsg) -> Self {
+        BotAnswer {
+            text: msg.text,
+            images: msg.images,
+            sources: msg.sources,
+            suggested_replies: msg.suggest_replys,
+            ..Default::default()
+        }
+    }
+}
@@ -1,6 +1,13 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display};
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+
+use super::{
+    citation_type::CitedText,
+    error_type::{BingError, ErrorKind},
+    rollover_type::ChatRollover,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Image {
     pub name: String,
     pub url: String,
@@ -12,7 +19,7 @@ impl Display for Image {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Limit {
     #[serde(rename = "maxNumUserMessagesInConversation")]
     pub max_num_user_messages: u8,
@@ -34,7 +41,7 @@ impl Display for Limit {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
 pub struct SourceAttribution {
     #[serde(rename = "providerDisplayName")]
     pub display_name: Option<String>,
@@ -71,25 +78,157 @@ impl Display for SourceAttribution {
     }
 }
 
+// A web page bing grounded the answer on
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+impl Display for WebResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}]({})", self.title, self.url)?;
+        if !self.snippet.is_empty() {
+            write!(f, "\n{}", self.snippet)?;
+        }
+        Ok(())
+    }
+}
+
+// An answer bing took back, with the fields of the message that tell why
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Withdrawal {
+    // The answer text streamed before it was withdrawn, empty if none was
+    pub text: String,
+    pub message_id: Option<String>,
+    // e.g. "Offensive", "OffenseTrigger"
+    pub offense: Option<String>,
+    // e.g. "Apology", "DeepLeo"
+    pub content_origin: Option<String>,
+    pub hidden_text: Option<String>,
+}
+
+// A `GeneratedCode` message
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedCode {
+    pub language: Option<String>,
+    pub code: String,
+}
+
+impl Display for GeneratedCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "```{}\n{}\n```",
+            self.language.as_deref().unwrap_or_default(),
+            self.code
+        )
+    }
+}
+
+// The change of the answer text since the last one.
+// Truncate the text to `offset` bytes and append `text`, `offset` is less than the
+// previous length only when bing rewrote earlier text.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TextDelta {
+    pub offset: usize,
+    pub text: String,
+}
+
+impl TextDelta {
+    pub fn is_rewrite(&self, previous: &str) -> bool {
+        self.offset < previous.len()
+    }
+
+    pub fn apply_to(&self, text: &mut String) {
+        text.truncate(self.offset);
+        text.push_str(&self.text);
+    }
+}
+
+// A `BingError` of the answer as `BotResp::Error` carries it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AnswerError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl From<&BingError> for AnswerError {
+    fn from(error: &BingError) -> Self {
+        AnswerError {
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum BotResp {
+    // The whole answer so far
     Text(String),
+    // Only sent with `TextMode::Delta` or `TextMode::Both`
+    TextDelta(TextDelta),
     SuggestReply(Vec<String>),
     Notice(String),
     Image(Vec<Image>),
     Apology(String),
     SourceAttribution(Vec<SourceAttribution>),
+    // The final answer with its `[^n^]` markers resolved to sources
+    Citations(CitedText),
     Limit(Limit),
+    // A web search bing ran for the answer
+    SearchQuery(String),
+    SearchResults(Vec<WebResult>),
+    // Bing ended the conversation, `Chat::is_disengaged` is true from now on
+    Disengaged(String),
+    // e.g. "Analyzing the image"
+    Progress(String),
+    // The steps of `InternalTasksMessage`
+    Tasks(Vec<String>),
+    GeneratedCode(GeneratedCode),
+    // The text of a `RenderCardRequest`
+    RenderCard(String),
+    // The text of a `RenderContentRequest`
+    RenderContent(String),
+    Disclaimer(String),
+    // An action bing asks the client to take, e.g. open a page
+    ActionRequest(String),
+    // A question bing wants the user to confirm before it goes on
+    ConfirmationCard(String),
+    // What bing took as the context of the question, e.g. the open page
+    Context(String),
+    // The query of the ads shown with the answer
+    AdsQuery(String),
+    // The text of a `SemanticSerp`, bing's own search results page
+    SemanticSerp(String),
+    // The prompt of a song bing asks Suno to make, the song itself is not fetched
+    SongPrompt(String),
+    // The streamed answer was replaced, e.g. by an apology.
+    // Sent once per message, even if nothing had been streamed, the next `Text` starts over.
+    Retracted(Withdrawal),
+    // The streamed answer was flagged by bing's content filter, `offense` holds the verdict
+    Moderated(Withdrawal),
+    // Any other `messageType`, or a `GenerateContentQuery` that can't be handled,
+    // e.g. an image without `messageId`
+    Other { message_type: String, text: String },
+    // An error of the answer, only sent by `BingClient::ask_stream`, which cannot yield `Err`.
+    // `kind` tells e.g. a throttled account from a closed connection
+    Error(AnswerError),
+    // The chat was at bing's message limit, the question went to `ChatRollover.chat`.
+    // Sent first, see `RolloverPolicy`
+    Rollover(ChatRollover),
 }
 
 impl fmt::Display for BotResp {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Text(content) => write!(f, "{}", content),
+            Self::TextDelta(delta) => write!(f, "{}", delta.text),
             Self::SuggestReply(content) => {
                 let mut s = String::new();
                 for (index, suggest) in content.iter().enumerate() {
-                    s += &format!("{index}: {suggest}");
+                    s += &format!("{}. {}\n", index + 1, suggest);
                 }
                 write!(f, "{}", s)
             }
@@ -110,6 +249,48 @@ impl fmt::Display for BotResp {
                 write!(f, "{}", rst)
             }
             Self::Limit(content) => write!(f, "{}", content),
+            Self::Citations(cited) => write!(f, "{}", cited),
+            Self::Disengaged(content) => write!(f, "{}", content),
+            Self::Progress(content) => write!(f, "{}", content),
+            Self::Tasks(tasks) => {
+                let mut rst = String::new();
+                for (index, task) in tasks.iter().enumerate() {
+                    rst += &format!("{}. {}\n", index + 1, task);
+                }
+                write!(f, "{}", rst)
+            }
+            Self::GeneratedCode(code) => write!(f, "{}", code),
+            Self::RenderCard(content) => write!(f, "{}", content),
+            Self::RenderContent(content) => write!(f, "{}", content),
+            Self::ActionRequest(content) => write!(f, "{}", content),
+            Self::ConfirmationCard(content) => write!(f, "{}", content),
+            Self::Context(content) => write!(f, "{}", content),
+            Self::AdsQuery(query) => write!(f, "{}", query),
+            Self::SemanticSerp(content) => write!(f, "{}", content),
+            Self::SongPrompt(prompt) => write!(f, "{}", prompt),
+            Self::Disclaimer(content) => write!(f, "{}", content),
+            Self::Other { text, .. } => write!(f, "{}", text),
+            Self::Error(error) => write!(f, "Error: {}", error.message),
+            Self::Rollover(rollover) => write!(
+                f,
+                "Continued in a new chat: {}",
+                rollover.chat.conversation_id
+            ),
+            Self::Retracted(withdrawal) => write!(f, "Retracted: {}", withdrawal.text),
+            Self::Moderated(withdrawal) => write!(
+                f,
+                "Moderated ({}): {}",
+                withdrawal.offense.as_deref().unwrap_or("Unknown"),
+                withdrawal.text
+            ),
+            Self::SearchQuery(query) => write!(f, "Searching for: {}", query),
+            Self::SearchResults(results) => {
+                let mut rst = String::new();
+                for result in results {
+                    rst += &(result.to_string() + "\n");
+                }
+                write!(f, "{}", rst)
+            }
         }
     }
 }
@@ -3,6 +3,14 @@ use serde::{Deserialize, Serialize};
 
 use std::fmt::{Display, Formatter, Result};
 
+// An image of the history that could not be loaded
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ImageError {
+    // The url that was polled
+    pub url: String,
+    pub error: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EasyMsg {
     pub author: String,
@@ -10,6 +18,11 @@ pub struct EasyMsg {
     pub images: Vec<Image>,
     pub sources: Vec<SourceAttribution>,
     pub suggest_replys: Vec<String>,
+    // The urls of the images bing drew that are not loaded yet, see `BingClient::resolve_images`
+    #[serde(default)]
+    pub pending_images: Vec<String>,
+    #[serde(default)]
+    pub image_errors: Vec<ImageError>,
 }
 
 impl Display for EasyMsg {
@@ -18,19 +31,19 @@ impl Display for EasyMsg {
         if !self.images.is_empty() {
             write!(f, "\nImages:\n\n")?;
             for image in &self.images {
-                write!(f, "{}\n", image)?;
+                writeln!(f, "{}", image)?;
             }
         }
         if !self.sources.is_empty() {
             write!(f, "\nSources:\n\n")?;
             for (index, source) in self.sources.iter().enumerate() {
-                write!(f, "{index}. {}\n", source)?;
+                writeln!(f, "{}. {}", index + 1, source)?;
             }
         }
         if !self.suggest_replys.is_empty() {
             write!(f, "\nSuggest Replys:\n\n")?;
             for (index, suggest) in self.suggest_replys.iter().enumerate() {
-                write!(f, "{index}. {}\n", suggest)?;
+                writeln!(f, "{}. {}", index + 1, suggest)?;
             }
         }
         Ok(())
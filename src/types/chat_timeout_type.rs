@@ -0,0 +1,48 @@
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+// Timeouts of an answer on the ChatHub websocket, `None` waits forever
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct ChatTimeouts {
+    // Connecting and the `{"protocol":"json","version":1}` handshake
+    pub handshake: Option<Duration>,
+    // From sending the question to the first response
+    pub first_token: Option<Duration>,
+    // Between two frames, the server's type 6 pings count as frames
+    pub idle: Option<Duration>,
+    // The whole answer, without drawing images
+    pub total: Option<Duration>,
+}
+
+impl Default for ChatTimeouts {
+    fn default() -> Self {
+        ChatTimeouts {
+            handshake: Some(Duration::from_secs(30)),
+            first_token: Some(Duration::from_secs(60)),
+            idle: Some(Duration::from_secs(60)),
+            total: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutPhase {
+    Handshake,
+    FirstToken,
+    Idle,
+    Total,
+}
+
+impl fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = match self {
+            TimeoutPhase::Handshake => "ChatHub handshake",
+            TimeoutPhase::FirstToken => "first response",
+            TimeoutPhase::Idle => "next frame",
+            TimeoutPhase::Total => "whole answer",
+        };
+        write!(f, "{}", phase)
+    }
+}
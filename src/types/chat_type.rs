@@ -1,9 +1,39 @@
 
 
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use rand::RngCore as _;
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
 use super::plugin_type::Plugin;
+
+// Where a conversation stands, sent with every question
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionState {
+    // Questions bing has answered in this conversation, from `Limit.num_user_messages`
+    // of the last answer or the history of `get_chat_messages`
+    pub turns: u32,
+    // The `traceId` of the session, the same for every question
+    pub trace_id: Option<String>,
+    // `Limit.max_num_user_messages` of the last answer
+    pub max_turns: Option<u32>,
+    // Put before the questions until bing answers one, set by `BingClient::rollover`
+    pub seed: Option<String>,
+}
+
+impl SessionState {
+    pub fn is_limit_reached(&self) -> bool {
+        self.max_turns.is_some_and(|max_turns| self.turns >= max_turns)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Chat {
     #[serde(rename = "conversationId")]
@@ -22,6 +52,14 @@ pub struct Chat {
     pub x_sydney_conversationsignature: RwLock<Option<String>>,
     #[serde(skip)]
     pub x_sydney_encryptedconversationsignature: RwLock<Option<String>>,
+    // When the signatures were fetched, `None` if they were never fetched or were rejected
+    #[serde(skip)]
+    pub signature_time: Mutex<Option<Instant>>,
+    // Set when bing sends a `Disengaged` message, bing refuses to continue the conversation
+    #[serde(skip)]
+    pub disengaged: AtomicBool,
+    #[serde(default)]
+    pub session: Mutex<SessionState>,
 }
 
 impl Chat {
@@ -40,13 +78,76 @@ impl Chat {
             chat_name: self.chat_name.clone(),
             conversation_signature: self.conversation_signature.clone(),
             tone: self.tone.clone(),
-            create_time_utc: self.create_time_utc.clone(),
-            update_time_utc: self.update_time_utc.clone(),
+            create_time_utc: self.create_time_utc,
+            update_time_utc: self.update_time_utc,
             plugins: self.plugins.clone(),
             x_sydney_conversationsignature: x1.into(),
             x_sydney_encryptedconversationsignature: x2.into(),
+            signature_time: (*self.signature_time.lock().unwrap()).into(),
+            disengaged: self.is_disengaged().into(),
+            session: self.session().into(),
         }
     }
+
+    pub fn session(&self) -> SessionState {
+        self.session.lock().unwrap().clone()
+    }
+
+    pub fn is_start_of_session(&self) -> bool {
+        self.session.lock().unwrap().turns == 0
+    }
+
+    // Bing counts `turns` questions so far
+    pub(crate) fn set_turns(&self, turns: u32) {
+        self.session.lock().unwrap().turns = turns;
+    }
+
+    pub(crate) fn set_max_turns(&self, max_turns: u32) {
+        self.session.lock().unwrap().max_turns = Some(max_turns);
+    }
+
+    pub(crate) fn set_seed(&self, seed: String) {
+        self.session.lock().unwrap().seed = Some(seed);
+    }
+
+    pub(crate) fn seed(&self) -> Option<String> {
+        self.session.lock().unwrap().seed.clone()
+    }
+
+    pub(crate) fn clear_seed(&self) {
+        self.session.lock().unwrap().seed = None;
+    }
+
+    // Creates the trace id on the first question
+    pub(crate) fn trace_id(&self) -> String {
+        self.session
+            .lock()
+            .unwrap()
+            .trace_id
+            .get_or_insert_with(|| {
+                let mut rng = rand::thread_rng();
+                let mut rand_buf: [u8; 16] = [0; 16];
+                rng.fill_bytes(&mut rand_buf);
+                hex::encode(rand_buf)
+            })
+            .clone()
+    }
+
+    // The signatures were fetched less than `ttl` ago and were not rejected since
+    pub fn has_fresh_signature(&self, ttl: Duration) -> bool {
+        self.signature_time
+            .lock()
+            .unwrap()
+            .is_some_and(|time| time.elapsed() < ttl)
+    }
+
+    pub(crate) fn set_signature_time(&self, time: Option<Instant>) {
+        *self.signature_time.lock().unwrap() = time;
+    }
+
+    pub fn is_disengaged(&self) -> bool {
+        self.disengaged.load(Ordering::SeqCst)
+    }
 }
 
 impl std::fmt::Display for Chat {
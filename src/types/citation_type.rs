@@ -0,0 +1,50 @@
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+
+use super::bot_easy_resp_type::SourceAttribution;
+
+// A `[^n^]` marker of the answer text
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Citation {
+    // `n` of the marker, 1-based index of `sourceAttributions`
+    pub number: usize,
+    // Byte range of the marker in `CitedText.raw`
+    pub raw_start: usize,
+    pub raw_end: usize,
+    // Byte range of the link in `CitedText.clean`, empty if there is no source
+    pub clean_start: usize,
+    pub clean_end: usize,
+    // `None` if bing sent no source for `number`
+    pub source: Option<SourceAttribution>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct CitedText {
+    // The answer as bing sent it, with the `[^n^]` markers
+    pub raw: String,
+    // Every marker replaced by a markdown link `[n](url)`, or removed if it has no source
+    pub clean: String,
+    pub citations: Vec<Citation>,
+}
+
+impl CitedText {
+    // The sources cited at least once, in the order of first use
+    pub fn cited_sources(&self) -> Vec<(usize, &SourceAttribution)> {
+        let mut sources: Vec<(usize, &SourceAttribution)> = Vec::new();
+        for citation in &self.citations {
+            if let Some(source) = &citation.source {
+                if !sources.iter().any(|(number, _)| *number == citation.number) {
+                    sources.push((citation.number, source));
+                }
+            }
+        }
+        sources
+    }
+}
+
+impl Display for CitedText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.clean)
+    }
+}
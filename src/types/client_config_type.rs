@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::const_vars::{DEFAULT_SYDNEY_HOST, DEFAULT_WSS_HOST, DEFAULT_WWW_HOST};
+
+use super::{
+    chat_timeout_type::ChatTimeouts, cookie_report_type::CookieCheck, locale_type::Locale,
+};
+
+// Hosts are stored without a trailing slash, eg: "https://www.bing.com"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    pub www: String,
+    pub sydney: String,
+    pub wss: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints {
+            www: DEFAULT_WWW_HOST.to_string(),
+            sydney: DEFAULT_SYDNEY_HOST.to_string(),
+            wss: DEFAULT_WSS_HOST.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsMode {
+    // Needs the `rustls` feature
+    Rustls,
+    // Needs the `native-tls` feature
+    NativeTls,
+}
+
+impl Default for TlsMode {
+    fn default() -> Self {
+        if cfg!(feature = "rustls") {
+            TlsMode::Rustls
+        } else {
+            TlsMode::NativeTls
+        }
+    }
+}
+
+// How the answer text is streamed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextMode {
+    // `BotResp::Text` with the whole answer so far
+    #[default]
+    Cumulative,
+    // `BotResp::TextDelta` with only the changed text
+    Delta,
+    // `BotResp::TextDelta` followed by `BotResp::Text`
+    Both,
+}
+
+// What `BingClient::rollover` does once a chat reached bing's message limit
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub enum RolloverPolicy {
+    // Keep the chat, bing refuses further questions
+    #[default]
+    Off,
+    // Create a new chat, its first question starts with `preamble` and the last
+    // `keep_messages` messages of the old chat
+    NewChat {
+        keep_messages: usize,
+        preamble: String,
+    },
+}
+
+impl RolloverPolicy {
+    pub fn new_chat(keep_messages: usize) -> Self {
+        RolloverPolicy::NewChat {
+            keep_messages,
+            preamble: "Continue our previous conversation, which ended with:".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProxyAuth {
+    pub username: String,
+    pub password: String,
+}
+
+// Proxy urls look like "http://127.0.0.1:8080", "https://proxy.example.com:443",
+// "socks5://127.0.0.1:1080" or "socks5h://127.0.0.1:1080",
+// credentials can be given in the url or by `auth`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProxyConfig {
+    // Used for every scheme without its own proxy url
+    pub all: Option<String>,
+    // Used for http:// and ws:// urls
+    pub http: Option<String>,
+    // Used for https:// and wss:// urls
+    pub https: Option<String>,
+    pub auth: Option<ProxyAuth>,
+}
+
+impl ProxyConfig {
+    pub fn is_empty(&self) -> bool {
+        self.all.is_none() && self.http.is_none() && self.https.is_none()
+    }
+
+    pub(crate) fn url_for_scheme(&self, scheme: &str) -> Option<&str> {
+        let proxy = match scheme {
+            "http" | "ws" => self.http.as_ref(),
+            "https" | "wss" => self.https.as_ref(),
+            _ => None,
+        };
+        proxy.or(self.all.as_ref()).map(|s| s.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ClientConfig {
+    pub endpoints: Endpoints,
+    pub request_timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    pub user_agent: Option<String>,
+    // Extra headers sent with every http request and the ChatHub handshake
+    pub headers: Vec<(String, String)>,
+    pub tls_mode: TlsMode,
+    pub accept_invalid_certs: bool,
+    pub proxy: ProxyConfig,
+    pub locale: Locale,
+    pub cookie_check: CookieCheck,
+    // Cookies that must be present and unexpired, eg: "_U"
+    pub required_cookies: Vec<String>,
+    pub text_mode: TextMode,
+    // How long `StopHandle::stop` waits for bing to confirm
+    pub stop_timeout: Duration,
+    pub chat_timeouts: ChatTimeouts,
+    pub rollover: RolloverPolicy,
+    // How often a `ChatSession` pings bing between answers
+    pub heartbeat_interval: Duration,
+    // How long the signatures of a chat are reused before they are fetched again
+    pub signature_ttl: Duration,
+    // How many images of the history are loaded at once
+    pub image_concurrency: usize,
+}
+
+// Not derived: `accept_invalid_certs` follows the `allow-invalid-tls` feature
+#[allow(clippy::derivable_impls)]
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            endpoints: Endpoints::default(),
+            request_timeout: None,
+            connect_timeout: None,
+            user_agent: None,
+            headers: Vec::new(),
+            tls_mode: TlsMode::default(),
+            accept_invalid_certs: cfg!(feature = "allow-invalid-tls"),
+            proxy: ProxyConfig::default(),
+            locale: Locale::default(),
+            cookie_check: CookieCheck::default(),
+            required_cookies: vec!["_U".to_string()],
+            text_mode: TextMode::default(),
+            stop_timeout: Duration::from_secs(5),
+            chat_timeouts: ChatTimeouts::default(),
+            rollover: RolloverPolicy::default(),
+            heartbeat_interval: Duration::from_secs(15),
+            signature_ttl: Duration::from_secs(30 * 60),
+            image_concurrency: 4,
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::utils::process_bot_resp::process_source_msg;
+
+use super::{
+    bot_easy_resp_type::{Image, SourceAttribution},
+    chat_msg_type::EasyMsg,
+    user_input_type::Tone,
+    Result,
+};
+
+// The `feedback` of a message, `type` is e.g. "None", "Upvote", "Downvote"
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Feedback {
+    #[serde(rename = "type")]
+    pub feedback_type: Option<String>,
+    pub tag: Option<Value>,
+    #[serde(rename = "updatedOn")]
+    pub updated_on: Option<String>,
+}
+
+// A field of another JSON type is `None`, instead of failing the whole conversation
+fn lenient<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    Ok(T::deserialize(Value::deserialize(deserializer)?).ok())
+}
+
+// A message of the GetConversation response, with every field bing sent
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ChatMessage {
+    // "user" or "bot"
+    #[serde(default)]
+    pub author: String,
+    pub text: Option<String>,
+    #[serde(rename = "messageId")]
+    pub message_id: Option<String>,
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<String>,
+    pub timestamp: Option<String>,
+    // `None` for the answer itself, e.g. "InternalSearchQuery", "Disengaged", "Suggestion"
+    #[serde(rename = "messageType")]
+    pub message_type: Option<String>,
+    // e.g. "DeepLeo", "Apology", "user"
+    #[serde(rename = "contentOrigin")]
+    pub content_origin: Option<String>,
+    // "IMAGE" for a drawn image
+    #[serde(rename = "contentType")]
+    pub content_type: Option<String>,
+    pub offense: Option<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub feedback: Option<Feedback>,
+    // The plugin call of the message, e.g. `graphic_art(prompt="a cat")`
+    #[serde(default, deserialize_with = "lenient")]
+    pub invocation: Option<String>,
+    pub tone: Option<String>,
+    #[serde(rename = "hiddenText")]
+    pub hidden_text: Option<String>,
+    #[serde(rename = "spokenText")]
+    pub spoken_text: Option<String>,
+    #[serde(rename = "imageUrl")]
+    pub image_url: Option<String>,
+    #[serde(rename = "originalImageUrl")]
+    pub original_image_url: Option<String>,
+    #[serde(rename = "sourceAttributions", default)]
+    pub source_attributions: Vec<Value>,
+    #[serde(rename = "suggestedResponses")]
+    pub suggested_responses: Option<Vec<ChatMessage>>,
+    // `Null` when bing sent none
+    #[serde(rename = "adaptiveCards", default)]
+    pub adaptive_cards: Value,
+    pub scores: Option<Vec<Value>>,
+    // Fields not listed above, e.g. `privacy`, `inputMethod`, `locale`
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl ChatMessage {
+    pub fn is_from_user(&self) -> bool {
+        self.author == "user"
+    }
+
+    pub fn is_from_bot(&self) -> bool {
+        self.author == "bot"
+    }
+
+    // A question or an answer shown in the chat, the messages `get_chat_messages` keeps
+    pub fn is_shown(&self) -> bool {
+        (self.scores.is_some()
+            || self.suggested_responses.is_some()
+            || (self.content_type.is_some() && !self.adaptive_cards.is_null()))
+            && !self.author.is_empty()
+            && self.text.is_some()
+    }
+
+    pub fn tone(&self) -> Option<Tone> {
+        self.tone.as_deref().and_then(Tone::build_by_name)
+    }
+
+    pub fn sources(&self) -> Vec<SourceAttribution> {
+        process_source_msg(self.source_attributions.clone()).unwrap_or_default()
+    }
+
+    pub fn suggested_replies(&self) -> Vec<String> {
+        self.suggested_responses
+            .as_ref()
+            .map_or_else(Vec::new, |suggests| {
+                suggests
+                    .iter()
+                    .filter_map(|suggest| suggest.text.clone())
+                    .collect()
+            })
+    }
+
+    // The image the user attached to the question
+    pub fn user_image(&self) -> Option<Image> {
+        if !self.is_from_user() {
+            return None;
+        }
+        self.image_url
+            .as_ref()
+            .or(self.original_image_url.as_ref())
+            .map(|url| Image {
+                name: "user_image_attachment.jpg".to_string(),
+                url: url.to_owned(),
+            })
+    }
+
+    // The urls to poll for the images bing drew, empty for any other message
+    pub fn image_urls(&self) -> Vec<String> {
+        if !self.is_from_bot() || self.content_type.as_deref() != Some("IMAGE") {
+            return Vec::new();
+        }
+        self.adaptive_cards
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|card| card["body"].as_array())
+            .flatten()
+            .filter_map(|block| block["persistentUrl"].as_str())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+// The images bing drew are left in `pending_images`
+impl From<&ChatMessage> for EasyMsg {
+    fn from(msg: &ChatMessage) -> Self {
+        EasyMsg {
+            author: msg.author.clone(),
+            text: msg.text.clone().unwrap_or_default(),
+            images: msg.user_image().into_iter().collect(),
+            sources: msg.sources(),
+            suggest_replys: msg.suggested_replies(),
+            pending_images: msg.image_urls(),
+            image_errors: Vec::new(),
+        }
+    }
+}
+
+// The GetConversation response, returned by `BingClient::get_conversation`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Conversation {
+    #[serde(rename = "conversationId", default)]
+    pub conversation_id: String,
+    #[serde(rename = "clientId")]
+    pub client_id: Option<String>,
+    #[serde(rename = "chatName")]
+    pub chat_name: Option<String>,
+    #[serde(rename = "conversationExpiryTime")]
+    pub conversation_expiry_time: Option<String>,
+    // Every message, including searches, suggestions and other internal ones
+    #[serde(default)]
+    pub messages: Vec<ChatMessage>,
+    pub result: Option<Result>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl Conversation {
+    // The questions and answers shown in the chat
+    pub fn shown_messages(&self) -> impl Iterator<Item = &ChatMessage> {
+        self.messages.iter().filter(|msg| msg.is_shown())
+    }
+
+    // The questions of the user, what bing counts as turns
+    pub fn turns(&self) -> usize {
+        self.messages
+            .iter()
+            .filter(|msg| msg.is_from_user())
+            .count()
+    }
+
+    // `maxNumUserMessagesInConversation` of the `throttling` of the conversation,
+    // or of its latest message that has one
+    pub fn max_turns(&self) -> Option<u32> {
+        let max_turns = |extra: &HashMap<String, Value>| {
+            extra.get("throttling")?["maxNumUserMessagesInConversation"]
+                .as_u64()
+                .map(|max_turns| max_turns as u32)
+        };
+        max_turns(&self.extra).or_else(|| {
+            self.messages
+                .iter()
+                .rev()
+                .find_map(|msg| max_turns(&msg.extra))
+        })
+    }
+}
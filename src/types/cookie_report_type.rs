@@ -0,0 +1,87 @@
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+
+// What `BingClient::build` does with a `CookieReport`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CookieCheck {
+    // No checks
+    Off,
+    // The report is kept in `BingClient.cookie_report`, the client is built anyway
+    #[default]
+    Report,
+    // Refuses to build with `BingError::InvalidCookies` if a required cookie is missing or expired
+    Strict,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExpiredCookie {
+    pub name: String,
+    pub domain: String,
+    // unix timestamp in seconds
+    pub expiration_date: f64,
+    pub required: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ForeignCookie {
+    pub name: String,
+    pub domain: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CookieReport {
+    // Required cookies that are not in the input at all
+    pub missing: Vec<String>,
+    // Cookies whose `expirationDate` has passed, they are dropped.
+    // Only known for `Cookie::JsonPath` and `Cookie::JsonStr`.
+    pub expired: Vec<ExpiredCookie>,
+    // Cookies whose domain does not fit the www host, they are left out of the cookie jar
+    pub foreign: Vec<ForeignCookie>,
+}
+
+impl CookieReport {
+    // No required cookie is missing or expired
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty() && !self.expired.iter().any(|cookie| cookie.required)
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.expired.is_empty() && self.foreign.is_empty()
+    }
+}
+
+impl Display for CookieReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            return write!(f, "All cookies are fine.");
+        }
+        let mut parts = Vec::new();
+        if !self.missing.is_empty() {
+            parts.push(format!("Missing: {}", self.missing.join(", ")));
+        }
+        if !self.expired.is_empty() {
+            let expired = self
+                .expired
+                .iter()
+                .map(|cookie| {
+                    let expired_at =
+                        chrono::DateTime::from_timestamp(cookie.expiration_date as i64, 0)
+                            .map(|dt| dt.to_rfc3339())
+                            .unwrap_or_default();
+                    format!("{} ({}, {})", cookie.name, cookie.domain, expired_at)
+                })
+                .collect::<Vec<_>>();
+            parts.push(format!("Expired: {}", expired.join(", ")));
+        }
+        if !self.foreign.is_empty() {
+            let foreign = self
+                .foreign
+                .iter()
+                .map(|cookie| format!("{} ({})", cookie.name, cookie.domain))
+                .collect::<Vec<_>>();
+            parts.push(format!("Foreign domain: {}", foreign.join(", ")));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
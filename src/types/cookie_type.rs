@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum Cookie {
     // Json means format like this:
     // [
@@ -22,4 +25,19 @@ pub enum Cookie {
     // SnrOvr=X=rebateson;SRCHUSR=DOB=20240323&T=1712299341000&TPC=1711617907000&POEX=W; ······
     HeadPath(String),
     HeadStr(String),
+    // Netscape means the `cookies.txt` format of curl and wget:
+    // .bing.com	TRUE	/	TRUE	1743827661	SnrOvr	X=rebateson
+    NetscapePath(String),
+    NetscapeStr(String),
+    // A HAR file saved from the network panel of the devtools,
+    // the cookies sent to and set by bing in its requests are used
+    HarPath(String),
+    // `cookies.sqlite` of a Firefox profile, needs the `browser-cookies` feature
+    FirefoxDb(String),
+    // `Cookies` of a Chromium (Chrome, Edge, ···) profile on Linux, needs the `browser-cookies` feature.
+    // `password` is the "Chromium Safe Storage" secret of the keyring, only needed for "v11" values
+    ChromiumDb {
+        path: String,
+        password: Option<String>,
+    },
 }
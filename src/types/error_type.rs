@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    bot_answer_type::BotAnswer, chat_timeout_type::TimeoutPhase, cookie_report_type::CookieReport,
+    Result as ServiceResult,
+};
+
+#[derive(Debug, Error)]
+pub enum BingError {
+    // Connection failed, timed out or the body could not be read
+    #[error("Network Error; {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("WebSocket Error; {0}")]
+    WebSocket(#[from] Box<tokio_tungstenite::tungstenite::Error>),
+    // Unexpected status code that is not one of the cases below
+    #[error("{action} Failed; StatusCode: {status}; Body: {body}")]
+    Http {
+        action: &'static str,
+        status: u16,
+        body: String,
+    },
+    // `result.value` of the response is not "Success" and not one of the cases below
+    #[error("{action} Failed; Error Value: {value}; Error Message: {message:?}")]
+    Service {
+        action: &'static str,
+        value: String,
+        message: Option<String>,
+    },
+    // The cookie is expired or invalid
+    #[error("{action} Failed; Unauthorized; StatusCode: {status:?}; Error Message: {message:?}")]
+    Unauthorized {
+        action: &'static str,
+        status: Option<u16>,
+        message: Option<String>,
+    },
+    #[error("{action} Failed; Throttled; StatusCode: {status:?}; Error Message: {message:?}")]
+    Throttled {
+        action: &'static str,
+        status: Option<u16>,
+        message: Option<String>,
+    },
+    // Solve the captcha in a browser with the same cookie, then retry
+    #[error("{action} Failed; Captcha Challenge; Error Message: {message:?}")]
+    CaptchaChallenge {
+        action: &'static str,
+        message: Option<String>,
+    },
+    // The conversation signature is expired or the conversation does not exist
+    #[error("{action} Failed; Invalid Session; Error Message: {message:?}")]
+    InvalidSession {
+        action: &'static str,
+        message: Option<String>,
+    },
+    #[error("{action} Failed; No {header} in resp headers.")]
+    MissingHeader {
+        action: &'static str,
+        header: &'static str,
+    },
+    // Cookies could not be loaded or saved
+    #[error("Cookie Error; {0}")]
+    Cookie(String),
+    // Refused by `CookieCheck::Strict`
+    #[error("Invalid Cookies; {0}")]
+    InvalidCookies(Box<CookieReport>),
+    #[error("Invalid Header; {0}")]
+    InvalidHeader(String),
+    #[error("Json Error; {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("IO Error; {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Image Error; {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Base64 Error; {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("Proxy Error; {0}")]
+    Proxy(String),
+    // The config can not be used, eg: a tls mode that is not enabled by crate features
+    #[error("Config Error; {0}")]
+    Config(String),
+    #[error("Drawing Failed; {0}")]
+    Draw(String),
+    #[error("{0} Failed; Timed out.")]
+    Timeout(&'static str),
+    // A ChatHub frame that could not be parsed, the answer goes on
+    #[error("Ask Bing Copilot Failed; Invalid Frame: {reason}; Frame: {frame}")]
+    InvalidFrame { frame: String, reason: String },
+    // The ChatHub was closed before the final frame, `partial_text` is the answer received so far
+    #[error("Ask Bing Copilot Failed; ChatHub Closed; Reason: {reason:?}")]
+    ChatClosed {
+        reason: Option<String>,
+        partial_text: String,
+    },
+    // The ChatHub stalled, `partial_text` is the answer received so far
+    #[error("Ask Bing Copilot Failed; Timed out waiting for the {phase}.")]
+    ChatTimeout {
+        phase: TimeoutPhase,
+        partial_text: String,
+    },
+    #[error("Task Error; {0}")]
+    Task(#[from] tokio::task::JoinError),
+    // Drawing or saving an image of the answer failed, the rest of the answer is complete
+    #[error("Answer Image Failed; {0}")]
+    AnswerImage(Box<BingError>),
+    // The answer of `ask` ended with `error`, `answer` is what was received before,
+    // its `finish` is `TimedOut` or `Error`
+    #[error("{error}")]
+    PartialAnswer {
+        answer: Box<BotAnswer>,
+        error: Box<BingError>,
+    },
+}
+
+// What went wrong, without the details of a `BingError`, see `BingError::kind`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Unauthorized,
+    Throttled,
+    CaptchaChallenge,
+    InvalidSession,
+    // Connection failed or the websocket broke
+    Network,
+    ChatClosed,
+    ChatTimeout,
+    InvalidFrame,
+    AnswerImage,
+    // Any other error, e.g. `Service` or `Http`
+    Other,
+}
+
+impl BingError {
+    // A `PartialAnswer` is of the kind of its error
+    pub fn kind(&self) -> ErrorKind {
+        match self.cause() {
+            BingError::Unauthorized { .. } => ErrorKind::Unauthorized,
+            BingError::Throttled { .. } => ErrorKind::Throttled,
+            BingError::CaptchaChallenge { .. } => ErrorKind::CaptchaChallenge,
+            BingError::InvalidSession { .. } => ErrorKind::InvalidSession,
+            BingError::Network(_) | BingError::WebSocket(_) => ErrorKind::Network,
+            BingError::ChatClosed { .. } => ErrorKind::ChatClosed,
+            BingError::ChatTimeout { .. } => ErrorKind::ChatTimeout,
+            BingError::InvalidFrame { .. } => ErrorKind::InvalidFrame,
+            BingError::AnswerImage(_) => ErrorKind::AnswerImage,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    // Maps a `result` whose value is not "Success"
+    pub fn from_service(action: &'static str, value: &str, message: Option<String>) -> BingError {
+        match value {
+            "UnauthorizedRequest" | "Unauthorized" | "Forbidden" => BingError::Unauthorized {
+                action,
+                status: None,
+                message,
+            },
+            "Throttled" => BingError::Throttled {
+                action,
+                status: None,
+                message,
+            },
+            "CaptchaChallenge" => BingError::CaptchaChallenge { action, message },
+            "InvalidSession" | "InvalidConversation" | "ConversationNotFound" => {
+                BingError::InvalidSession { action, message }
+            }
+            _ => BingError::Service {
+                action,
+                value: value.to_string(),
+                message,
+            },
+        }
+    }
+
+    pub fn from_result(action: &'static str, result: &ServiceResult) -> BingError {
+        BingError::from_service(action, &result.value, result.message.clone())
+    }
+
+    // Maps a response whose status code is not 2xx
+    pub fn from_status(action: &'static str, status: u16, body: String) -> BingError {
+        let message = if body.is_empty() { None } else { Some(body) };
+        match status {
+            401 | 403 => BingError::Unauthorized {
+                action,
+                status: Some(status),
+                message,
+            },
+            429 => BingError::Throttled {
+                action,
+                status: Some(status),
+                message,
+            },
+            _ if message
+                .as_deref()
+                .is_some_and(|body| body.to_lowercase().contains("captcha")) =>
+            {
+                BingError::CaptchaChallenge { action, message }
+            }
+            _ => BingError::Http {
+                action,
+                status,
+                body: message.unwrap_or_default(),
+            },
+        }
+    }
+
+    // The error behind a `PartialAnswer`, or this one
+    pub fn cause(&self) -> &BingError {
+        match self {
+            BingError::PartialAnswer { error, .. } => error.cause(),
+            e => e,
+        }
+    }
+
+    pub fn partial_answer(&self) -> Option<&BotAnswer> {
+        match self {
+            BingError::PartialAnswer { answer, .. } => Some(answer),
+            _ => None,
+        }
+    }
+
+    // Bing refused the conversation signature, a new one may work
+    pub fn is_signature_rejected(&self) -> bool {
+        match self {
+            BingError::InvalidSession { .. } => true,
+            BingError::Unauthorized { status, .. } => *status == Some(401),
+            BingError::WebSocket(e) => matches!(
+                e.as_ref(),
+                tokio_tungstenite::tungstenite::Error::Http(resp) if resp.status() == 401
+            ),
+            _ => false,
+        }
+    }
+
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            BingError::Network(e) => e.status().map(|status| status.as_u16()),
+            BingError::Http { status, .. } => Some(*status),
+            BingError::Unauthorized { status, .. } | BingError::Throttled { status, .. } => *status,
+            _ => None,
+        }
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for BingError {
+    fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
+        BingError::WebSocket(Box::new(e))
+    }
+}
+
+impl From<reqwest::header::ToStrError> for BingError {
+    fn from(e: reqwest::header::ToStrError) -> Self {
+        BingError::InvalidHeader(e.to_string())
+    }
+}
+
+impl From<reqwest::header::InvalidHeaderValue> for BingError {
+    fn from(e: reqwest::header::InvalidHeaderValue) -> Self {
+        BingError::InvalidHeader(e.to_string())
+    }
+}
+
+impl From<reqwest::header::InvalidHeaderName> for BingError {
+    fn from(e: reqwest::header::InvalidHeaderName) -> Self {
+        BingError::InvalidHeader(e.to_string())
+    }
+}
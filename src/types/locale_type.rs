@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+
+use super::error_type::BingError;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+    // in meters
+    pub radius: i32,
+    pub country_name: String,
+    // state or province
+    pub admin1_name: String,
+    // city
+    pub populated_place_name: String,
+    pub post_code: String,
+    // hours from UTC
+    pub utc_offset: i32,
+}
+
+impl Location {
+    pub fn redmond() -> Location {
+        Location {
+            latitude: 47.639557,
+            longitude: -122.128159,
+            radius: 1000,
+            country_name: "United States".to_string(),
+            admin1_name: "Washington".to_string(),
+            populated_place_name: "Redmond".to_string(),
+            post_code: "98052".to_string(),
+            utc_offset: -8,
+        }
+    }
+}
+
+// Decides the locale, market, region, location and user ip of every chat request,
+// and the `_EDGE_CD` cookie of the client.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Locale {
+    // eg: "en-US"
+    pub locale: String,
+    // eg: "en-US"
+    pub market: String,
+    // eg: "US"
+    pub region: String,
+    pub location: Option<Location>,
+    pub user_ip_address: Option<String>,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::new("en-US").with_location(Location::redmond())
+    }
+}
+
+impl Locale {
+    // The region is the region subtag, eg: "de-DE" -> "DE", "zh-Hans-CN" -> "CN",
+    // and the market is the locale. Without a region subtag both come from the language,
+    // eg: "en" -> "US" and "en-US". An unknown language leaves the region empty,
+    // set it with `with_region` and `with_market`.
+    pub fn new(locale: &str) -> Locale {
+        let mut subtags = locale.split(['-', '_']);
+        let language = subtags.next().unwrap_or_default().to_lowercase();
+        let region = subtags.find(|subtag| {
+            (subtag.len() == 2 && subtag.chars().all(|c| c.is_ascii_alphabetic()))
+                || (subtag.len() == 3 && subtag.chars().all(|c| c.is_ascii_digit()))
+        });
+        let (market, region) = match region {
+            Some(region) => (locale.to_string(), region.to_uppercase()),
+            None => match default_region(&language) {
+                Some(region) => (format!("{language}-{region}"), region.to_string()),
+                None => (locale.to_string(), String::new()),
+            },
+        };
+        Locale {
+            locale: locale.to_string(),
+            market,
+            region,
+            location: None,
+            user_ip_address: None,
+        }
+    }
+
+    // Bing needs a region, eg: "US", and a market with one, eg: "en-US"
+    pub fn validate(&self) -> Result<(), BingError> {
+        let region_ok = |region: &str| {
+            (region.len() == 2 && region.chars().all(|c| c.is_ascii_uppercase()))
+                || (region.len() == 3 && region.chars().all(|c| c.is_ascii_digit()))
+        };
+        if !region_ok(&self.region) {
+            return Err(BingError::Config(format!(
+                "Locale {}: invalid region {:?}, set one with `Locale::with_region`.",
+                self.locale, self.region
+            )));
+        }
+        if !self.market.split_once('-').is_some_and(|(_, region)| region_ok(region)) {
+            return Err(BingError::Config(format!(
+                "Locale {}: invalid market {:?}, set one like \"en-US\" with `Locale::with_market`.",
+                self.locale, self.market
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn with_market(mut self, market: &str) -> Locale {
+        self.market = market.to_string();
+        self
+    }
+
+    pub fn with_region(mut self, region: &str) -> Locale {
+        self.region = region.to_string();
+        self
+    }
+
+    pub fn with_location(mut self, location: Location) -> Locale {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn with_user_ip_address(mut self, user_ip_address: &str) -> Locale {
+        self.user_ip_address = Some(user_ip_address.to_string());
+        self
+    }
+
+    pub(crate) fn edge_cd_cookie(&self) -> String {
+        format!(
+            "_EDGE_CD=m={}&u={}",
+            self.market.to_lowercase(),
+            self.locale.to_lowercase()
+        )
+    }
+}
+
+// The usual region of a language, for locales without one
+fn default_region(language: &str) -> Option<&'static str> {
+    let region = match language {
+        "ar" => "SA",
+        "cs" => "CZ",
+        "da" => "DK",
+        "de" => "DE",
+        "el" => "GR",
+        "en" => "US",
+        "es" => "ES",
+        "fi" => "FI",
+        "fr" => "FR",
+        "he" => "IL",
+        "hi" => "IN",
+        "hu" => "HU",
+        "id" => "ID",
+        "it" => "IT",
+        "ja" => "JP",
+        "ko" => "KR",
+        "nb" | "no" => "NO",
+        "nl" => "NL",
+        "pl" => "PL",
+        "pt" => "BR",
+        "ro" => "RO",
+        "ru" => "RU",
+        "sv" => "SE",
+        "th" => "TH",
+        "tr" => "TR",
+        "uk" => "UA",
+        "vi" => "VN",
+        "zh" => "CN",
+        _ => return None,
+    };
+    Some(region)
+}
@@ -1,18 +1,29 @@
 use serde::{Deserialize, Serialize};
 
+pub mod chat_timeout_type;
+pub mod citation_type;
 pub mod chat_type;
+pub mod conversation_type;
+pub mod client_config_type;
 pub mod client_info_type;
 pub mod create_chat_type;
 pub mod delete_chat_type;
+pub mod error_type;
+pub mod locale_type;
 pub mod user_input_type;
 pub mod plugin_type;
+pub mod bot_answer_type;
 pub mod bot_easy_resp_type;
 pub mod chat_msg_type;
 pub mod rename_chat_type;
+pub mod render_type;
+pub mod rollover_type;
+pub mod cookie_report_type;
 pub mod cookie_type;
+pub mod stop_outcome_type;
 pub mod update_conversation;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Result {
     pub value: String,
     pub message: Option<String>,
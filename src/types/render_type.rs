@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+// A part of a `BotAnswer`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Text,
+    Images,
+    Apologies,
+    // `BotAnswer.errors`
+    Errors,
+    Sources,
+    Suggestions,
+    Limit,
+    Notices,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkStyle {
+    // The title linking to the url, e.g. `[title](url)`
+    #[default]
+    Inline,
+    // The title followed by the url, e.g. `title: url`
+    Bare,
+}
+
+// Markdown dialect of `MarkdownRenderer`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarkdownFlavor {
+    #[default]
+    CommonMark,
+    // Slack mrkdwn: `*bold*` and `<url|title>`
+    Slack,
+}
+
+// Headings of the sections, an empty heading is left out
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct SectionHeadings {
+    pub images: String,
+    pub apologies: String,
+    pub errors: String,
+    pub sources: String,
+    pub suggestions: String,
+    pub limit: String,
+    pub notices: String,
+}
+
+impl Default for SectionHeadings {
+    fn default() -> Self {
+        SectionHeadings {
+            images: "Images".to_string(),
+            apologies: "Apology".to_string(),
+            errors: "Errors".to_string(),
+            sources: "Sources".to_string(),
+            suggestions: "Suggest Replys".to_string(),
+            limit: "Limits".to_string(),
+            notices: "Notices".to_string(),
+        }
+    }
+}
+
+impl SectionHeadings {
+    pub fn get(&self, section: Section) -> &str {
+        match section {
+            Section::Text => "",
+            Section::Images => &self.images,
+            Section::Apologies => &self.apologies,
+            Section::Errors => &self.errors,
+            Section::Sources => &self.sources,
+            Section::Suggestions => &self.suggestions,
+            Section::Limit => &self.limit,
+            Section::Notices => &self.notices,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct RenderOptions {
+    // The sections to render, in this order
+    pub sections: Vec<Section>,
+    pub headings: SectionHeadings,
+    pub link_style: LinkStyle,
+    // The number of the first item of a list
+    pub first_number: usize,
+    // Turn the `[^n^]` markers of the text into links to their sources
+    pub citations: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            sections: vec![
+                Section::Text,
+                Section::Images,
+                Section::Apologies,
+                Section::Errors,
+                Section::Sources,
+                Section::Suggestions,
+                Section::Limit,
+            ],
+            headings: SectionHeadings::default(),
+            link_style: LinkStyle::default(),
+            first_number: 1,
+            citations: true,
+        }
+    }
+}
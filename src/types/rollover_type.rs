@@ -0,0 +1,14 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::chat_type::Chat;
+
+// A chat that replaced one at bing's message limit
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChatRollover {
+    pub chat: Arc<Chat>,
+    pub previous_conversation_id: String,
+    // Sent before the first question of `chat`, empty if nothing was kept
+    pub summary: String,
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopStatus {
+    // Bing confirmed the stop with a type 3 frame
+    Acknowledged,
+    // The answer was complete before the stop reached bing
+    Finished,
+    // No confirmation within `ClientConfig.stop_timeout`
+    TimedOut,
+    // The connection was closed without a confirmation
+    Closed,
+}
+
+// What `StopHandle::stop` ended with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StopOutcome {
+    pub status: StopStatus,
+    // The answer text received before the stop
+    pub text: String,
+}
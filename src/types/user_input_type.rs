@@ -1,13 +1,17 @@
 use std::fmt::Display;
 
 use chrono::{DateTime, Utc};
-use rand::RngCore as _;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{client::BingClient, utils::image_base64::Image, vec_string};
 
-use super::{chat_type::Chat, plugin_type::Plugin};
+use super::{
+    chat_type::Chat,
+    error_type::BingError,
+    locale_type::{Locale, Location},
+    plugin_type::Plugin,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(non_snake_case)]
@@ -42,6 +46,11 @@ impl Arguments {
         chat: &Chat,
         client: &BingClient,
     ) -> Arguments {
+        // the summary of the chat this one replaced, cleared once bing answered
+        let text_message = match chat.seed() {
+            Some(seed) => format!("{seed}\n\n{text_message}"),
+            None => text_message,
+        };
         Arguments {
             source: "cib".to_string(),
             optionsSets: OptionsSets::from_tone(&tone, &plugins),
@@ -49,18 +58,13 @@ impl Arguments {
             sliceIds: SliceIds::from_tone(&tone),
             verbosity: "verbose".to_string(),
             scenario: "SERP".to_string(),
-            plugins: plugins,
-            traceId: {
-                let mut rng = rand::thread_rng();
-                let mut rand_buf: [u8; 16] = [0; 16];
-                rng.fill_bytes(&mut rand_buf);
-                hex::encode(rand_buf)
-            },
+            plugins,
+            traceId: chat.trace_id(),
             conversationHistoryOptionsSets: ConversationHistoryOptionsSets::default(),
             gptId: "copilot".to_string(),
-            isStartOfSession: true,
+            isStartOfSession: chat.is_start_of_session(),
             requestId: uuid.clone(),
-            message: Message::build(text_message, image_url, uuid),
+            message: Message::build(text_message, image_url, uuid, &client.config.locale),
             tone: tone.to_string(),
             extraExtensionParameters: ExtraExtensionParameters::default(),
             spokenTextMode: "None".to_string(),
@@ -425,26 +429,29 @@ pub struct LocationHints {
     pub Dma: i32,
 }
 
-impl Default for LocationHints {
-    fn default() -> Self {
+impl From<&Location> for LocationHints {
+    fn from(location: &Location) -> Self {
         LocationHints {
             SourceType: 1,
             RegionType: 2,
             Center: Center {
-                Latitude: 1.3056000471115112,
-                Longitude: 103.822998046875,
+                Latitude: location.latitude,
+                Longitude: location.longitude,
             },
-            Radius: 24902,
-            Name: "Singapore, Central Singapore Community Development Council".to_string(),
-            Accuracy: 24902,
+            Radius: location.radius,
+            Name: format!(
+                "{}, {}",
+                location.populated_place_name, location.admin1_name
+            ),
+            Accuracy: location.radius,
             FDConfidence: 0,
-            CountryName: "Singapore".to_string(),
+            CountryName: location.country_name.clone(),
             CountryConfidence: 8,
-            Admin1Name: "Central Singapore Community Development Council".to_string(),
-            PopulatedPlaceName: "Singapore".to_string(),
+            Admin1Name: location.admin1_name.clone(),
+            PopulatedPlaceName: location.populated_place_name.clone(),
             PopulatedPlaceConfidence: 0,
-            PostCodeName: "247964".to_string(),
-            UtcOffset: 8,
+            PostCodeName: location.post_code.clone(),
+            UtcOffset: location.utc_offset,
             Dma: 0,
         }
     }
@@ -453,8 +460,8 @@ impl Default for LocationHints {
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(non_snake_case)]
 pub struct Center {
-    Latitude: f64,
-    Longitude: f64,
+    pub Latitude: f64,
+    pub Longitude: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -463,9 +470,12 @@ pub struct Message {
     pub locale: String,
     pub market: String,
     pub region: String,
-    pub location: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub locationHints: Vec<LocationHints>,
-    pub userIpAddress: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub userIpAddress: Option<String>,
     pub timestamp: String,
     pub author: String,
     pub inputMethod: String,
@@ -480,17 +490,22 @@ pub struct Message {
 }
 
 impl Message {
-    pub fn build(text_message: String, image_url: Option<String>, uuid: String) -> Self {
-        Message {
-            locale: "en-US".to_string(),
-            market: "en-US".to_string(),
-            region: "US".to_string(),
-            location: "lat:47.639557;long:-122.128159;re=1000m;".to_string(),
-            locationHints: vec![LocationHints::default()],
-            userIpAddress: "13.212.211.208".to_string(),
+    pub fn build(
+        text_message: String,
+        image_url: Option<String>,
+        uuid: String,
+        locale: &Locale,
+    ) -> Self {
+        let mut message = Message {
+            locale: String::new(),
+            market: String::new(),
+            region: String::new(),
+            location: None,
+            locationHints: Vec::new(),
+            userIpAddress: None,
             timestamp: {
                 let dt: DateTime<Utc> = Utc::now();
-                format!("{}", dt.to_rfc3339())
+                dt.to_rfc3339()
             },
             author: "user".to_string(),
             inputMethod: "Keyboard".to_string(),
@@ -500,7 +515,27 @@ impl Message {
             messageType: "Chat".to_string(),
             requestId: uuid.clone(),
             messageId: uuid,
-        }
+        };
+        message.set_locale(locale);
+        message
+    }
+
+    pub fn set_locale(&mut self, locale: &Locale) {
+        self.locale = locale.locale.clone();
+        self.market = locale.market.clone();
+        self.region = locale.region.clone();
+        self.location = locale.location.as_ref().map(|location| {
+            format!(
+                "lat:{};long:{};re={}m;",
+                location.latitude, location.longitude, location.radius
+            )
+        });
+        self.locationHints = locale
+            .location
+            .as_ref()
+            .map(|location| vec![LocationHints::from(location)])
+            .unwrap_or_default();
+        self.userIpAddress = locale.user_ip_address.clone();
     }
 }
 
@@ -568,6 +603,9 @@ pub struct UserInput {
     pub invocationId: String,
     pub target: String,
     pub r#type: i32,
+    // Set by `with_locale`, its `_EDGE_CD` cookie is sent with the ChatHub handshake
+    #[serde(skip)]
+    pub locale: Option<Locale>,
 }
 
 impl UserInput {
@@ -578,7 +616,7 @@ impl UserInput {
         plugins: Vec<Plugin>,
         chat: &Chat,
         client: &BingClient,
-    ) -> Result<Self, anyhow::Error> {
+    ) -> Result<Self, BingError> {
         let uuid = Uuid::new_v4().to_string();
         let image_url = match image_attach {
             Some(image) => Some(client.gen_upload_image_url(image, chat).await?),
@@ -594,7 +632,7 @@ impl UserInput {
         let final_tone = {
             if let Some(name) = &chat.tone {
                 {}
-                if let Some(tone) = Tone::build_by_name(&name) {
+                if let Some(tone) = Tone::build_by_name(name) {
                     tone
                 } else {
                     tone
@@ -616,6 +654,36 @@ impl UserInput {
             invocationId: 0.to_string(),
             target: "chat".to_string(),
             r#type: 4,
+            locale: None,
         })
     }
+
+    // Overrides the client's locale for this request only, the `_EDGE_CD` cookie too
+    pub fn with_locale(mut self, locale: &Locale) -> Result<Self, BingError> {
+        locale.validate()?;
+        for argument in self.arguments.iter_mut() {
+            argument.message.set_locale(locale);
+        }
+        self.locale = Some(locale.clone());
+        Ok(self)
+    }
+
+    // Moves the question to `chat`, the new chat of a rollover
+    pub(crate) fn retarget(&mut self, chat: &Chat) {
+        for argument in self.arguments.iter_mut() {
+            argument.conversationId = chat.conversation_id.clone();
+            argument.traceId = chat.trace_id();
+            argument.isStartOfSession = chat.is_start_of_session();
+            if let Some(seed) = chat.seed() {
+                argument.message.text = format!("{seed}\n\n{}", argument.message.text);
+            }
+        }
+    }
+
+    // The invocation id of the stop of this question, the one after its own
+    pub(crate) fn stop_invocation_id(&self) -> String {
+        self.invocationId
+            .parse::<u64>()
+            .map_or_else(|_| format!("{}-stop", self.invocationId), |id| (id + 1).to_string())
+    }
 }
@@ -0,0 +1,251 @@
+use serde_json::Value;
+
+use crate::types::{
+    bot_answer_type::BotAnswer,
+    render_type::{LinkStyle, MarkdownFlavor, RenderOptions, Section},
+};
+
+// Turns a `BotAnswer` into text for a chat app, a web page or storage
+pub trait AnswerRenderer {
+    fn render(&self, answer: &BotAnswer) -> String;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownRenderer {
+    pub options: RenderOptions,
+    pub flavor: MarkdownFlavor,
+}
+
+// Telegram-style HTML: only `<b>` and `<a>`, lines are separated by `\n`
+#[derive(Debug, Clone, Default)]
+pub struct HtmlRenderer {
+    pub options: RenderOptions,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PlainRenderer {
+    pub options: RenderOptions,
+}
+
+// The fields of the answer for `options.sections`, and `finish`
+#[derive(Debug, Clone, Default)]
+pub struct JsonRenderer {
+    pub options: RenderOptions,
+    pub pretty: bool,
+}
+
+// What differs between the text renderers
+trait Markup {
+    fn escape(&self, text: &str) -> String;
+    fn heading(&self, heading: &str) -> String;
+    fn link(&self, title: &str, url: &str) -> String;
+    fn image(&self, name: &str, url: &str) -> String {
+        self.link(name, url)
+    }
+}
+
+impl Markup for MarkdownRenderer {
+    // Slack reads `&`, `<` and `>` as markup
+    fn escape(&self, text: &str) -> String {
+        match self.flavor {
+            MarkdownFlavor::CommonMark => text.to_owned(),
+            MarkdownFlavor::Slack => text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;"),
+        }
+    }
+    fn heading(&self, heading: &str) -> String {
+        match self.flavor {
+            MarkdownFlavor::CommonMark => format!("**{heading}**"),
+            MarkdownFlavor::Slack => format!("*{}*", self.escape(heading)),
+        }
+    }
+    fn link(&self, title: &str, url: &str) -> String {
+        match (self.options.link_style, self.flavor) {
+            (LinkStyle::Bare, _) => format!("{}: {url}", self.escape(title)),
+            (LinkStyle::Inline, MarkdownFlavor::CommonMark) => format!("[{title}]({url})"),
+            (LinkStyle::Inline, MarkdownFlavor::Slack) => format!("<{url}|{}>", self.escape(title)),
+        }
+    }
+    fn image(&self, name: &str, url: &str) -> String {
+        match (self.options.link_style, self.flavor) {
+            (LinkStyle::Inline, MarkdownFlavor::CommonMark) => format!("![{name}]({url})"),
+            _ => self.link(name, url),
+        }
+    }
+}
+
+impl Markup for HtmlRenderer {
+    fn escape(&self, text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+    fn heading(&self, heading: &str) -> String {
+        format!("<b>{}</b>", self.escape(heading))
+    }
+    fn link(&self, title: &str, url: &str) -> String {
+        match self.options.link_style {
+            LinkStyle::Inline => {
+                format!(
+                    "<a href=\"{}\">{}</a>",
+                    self.escape(url),
+                    self.escape(title)
+                )
+            }
+            LinkStyle::Bare => format!("{}: {}", self.escape(title), self.escape(url)),
+        }
+    }
+}
+
+impl Markup for PlainRenderer {
+    fn escape(&self, text: &str) -> String {
+        text.to_owned()
+    }
+    fn heading(&self, heading: &str) -> String {
+        format!("{heading}:")
+    }
+    fn link(&self, title: &str, url: &str) -> String {
+        match self.options.link_style {
+            LinkStyle::Inline => format!("{title} ({url})"),
+            LinkStyle::Bare => url.to_owned(),
+        }
+    }
+}
+
+impl AnswerRenderer for MarkdownRenderer {
+    fn render(&self, answer: &BotAnswer) -> String {
+        render_with(self, &self.options, answer)
+    }
+}
+
+impl AnswerRenderer for HtmlRenderer {
+    fn render(&self, answer: &BotAnswer) -> String {
+        render_with(self, &self.options, answer)
+    }
+}
+
+impl AnswerRenderer for PlainRenderer {
+    fn render(&self, answer: &BotAnswer) -> String {
+        render_with(self, &self.options, answer)
+    }
+}
+
+impl AnswerRenderer for JsonRenderer {
+    fn render(&self, answer: &BotAnswer) -> String {
+        let mut json = serde_json::to_value(answer).unwrap_or_default();
+        if let Value::Object(fields) = &mut json {
+            fields.retain(|key, _| {
+                let section = match key.as_str() {
+                    "text" | "citations" => Section::Text,
+                    "images" => Section::Images,
+                    "sources" => Section::Sources,
+                    "suggested_replies" => Section::Suggestions,
+                    "limit" => Section::Limit,
+                    "notices" => Section::Notices,
+                    "apologies" => Section::Apologies,
+                    "errors" => Section::Errors,
+                    _ => return true,
+                };
+                self.options.sections.contains(&section)
+            });
+        }
+        if self.pretty {
+            serde_json::to_string_pretty(&json).unwrap_or_default()
+        } else {
+            json.to_string()
+        }
+    }
+}
+
+fn render_with(markup: &impl Markup, options: &RenderOptions, answer: &BotAnswer) -> String {
+    let mut parts = Vec::new();
+    for section in &options.sections {
+        let items: Vec<String> = match section {
+            Section::Text => {
+                if !answer.text.is_empty() {
+                    parts.push(render_text(markup, options, answer));
+                }
+                continue;
+            }
+            Section::Images => answer
+                .images
+                .iter()
+                .map(|image| markup.image(&image.name, &image.url))
+                .collect(),
+            Section::Apologies => answer.apologies.iter().map(|a| markup.escape(a)).collect(),
+            Section::Errors => answer.errors.iter().map(|e| markup.escape(e)).collect(),
+            Section::Sources => answer
+                .sources
+                .iter()
+                .map(|source| {
+                    let url = source.see_more_url.as_deref().unwrap_or_default();
+                    let title = source.display_name.as_deref().unwrap_or(url);
+                    match (url.is_empty(), &source.image) {
+                        (false, _) => markup.link(title, url),
+                        (true, Some(image)) => markup.image(&image.name, &image.url),
+                        (true, None) => markup.escape(title),
+                    }
+                })
+                .collect(),
+            Section::Suggestions => answer
+                .suggested_replies
+                .iter()
+                .map(|s| markup.escape(s))
+                .collect(),
+            Section::Limit => answer
+                .limit
+                .iter()
+                .map(|limit| {
+                    format!(
+                        "{} of {}",
+                        limit.num_user_messages, limit.max_num_user_messages
+                    )
+                })
+                .collect(),
+            Section::Notices => answer.notices.iter().map(|n| markup.escape(n)).collect(),
+        };
+        if items.is_empty() {
+            continue;
+        }
+        let mut part = String::new();
+        let heading = options.headings.get(*section);
+        if !heading.is_empty() {
+            part += &markup.heading(heading);
+            part += "\n\n";
+        }
+        let lines: Vec<String> = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| format!("{}. {}", index + options.first_number, item))
+            .collect();
+        part += &lines.join("\n");
+        parts.push(part);
+    }
+    parts.join("\n\n")
+}
+
+// The `[^n^]` markers become links in the style of `markup`, or are removed without a source
+fn render_text(markup: &impl Markup, options: &RenderOptions, answer: &BotAnswer) -> String {
+    let cited = match &answer.citations {
+        Some(cited) if options.citations && cited.raw == answer.text => cited,
+        _ => return markup.escape(&answer.text),
+    };
+    let mut text = String::new();
+    let mut copied = 0;
+    for citation in &cited.citations {
+        text += &markup.escape(&cited.raw[copied..citation.raw_start]);
+        if let Some(url) = citation
+            .source
+            .as_ref()
+            .and_then(|s| s.see_more_url.as_ref())
+        {
+            text += &markup.link(&citation.number.to_string(), url);
+        }
+        copied = citation.raw_end;
+    }
+    text += &markup.escape(&cited.raw[copied..]);
+    text
+}
@@ -0,0 +1,56 @@
+use crate::types::{
+    bot_easy_resp_type::SourceAttribution,
+    citation_type::{Citation, CitedText},
+};
+
+// Resolves the `[^n^]` markers of `text`, `sources` is the whole `sourceAttributions`
+// list in bing's order, so `[^1^]` is `sources[0]`
+pub fn cite(text: &str, sources: &[SourceAttribution]) -> CitedText {
+    let mut clean = String::with_capacity(text.len());
+    let mut citations = Vec::new();
+    let mut copied = 0;
+    let mut search_from = 0;
+    while let Some(found) = text[search_from..].find("[^") {
+        let raw_start = search_from + found;
+        let Some((number, raw_end)) = parse_marker(text, raw_start) else {
+            search_from = raw_start + 2;
+            continue;
+        };
+        clean.push_str(&text[copied..raw_start]);
+        let source = number
+            .checked_sub(1)
+            .and_then(|index| sources.get(index))
+            .cloned();
+        let clean_start = clean.len();
+        if let Some(url) = source.as_ref().and_then(|s| s.see_more_url.as_ref()) {
+            clean.push_str(&format!("[{number}]({url})"));
+        }
+        citations.push(Citation {
+            number,
+            raw_start,
+            raw_end,
+            clean_start,
+            clean_end: clean.len(),
+            source,
+        });
+        copied = raw_end;
+        search_from = raw_end;
+    }
+    clean.push_str(&text[copied..]);
+    CitedText {
+        raw: text.to_owned(),
+        clean,
+        citations,
+    }
+}
+
+// `[^12^]` at `start`, returns the number and the end of the marker
+fn parse_marker(text: &str, start: usize) -> Option<(usize, usize)> {
+    let rest = &text[start + 2..];
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    if digits == 0 || !rest[digits..].starts_with("^]") {
+        return None;
+    }
+    let number = rest[..digits].parse().ok()?;
+    Some((number, start + 2 + digits + 2))
+}
@@ -0,0 +1,350 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::header::SET_COOKIE;
+use reqwest::Url;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{lookup_host, TcpStream},
+};
+use tokio_socks::tcp::Socks5Stream;
+use tokio_tungstenite::{
+    client_async_tls_with_config,
+    tungstenite::{self, client::IntoClientRequest, handshake::client::Request},
+    Connector, MaybeTlsStream, WebSocketStream,
+};
+
+use crate::types::{
+    client_config_type::{ClientConfig, TlsMode},
+    error_type::BingError,
+    locale_type::Locale,
+};
+
+use super::{cookie_jar::CookieJar, http_client::gen_config_headers};
+
+// A direct tcp connection or a tunnel through a proxy
+pub(crate) trait ChatHubIo: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> ChatHubIo for T {}
+
+pub(crate) type ChatHubStream = WebSocketStream<MaybeTlsStream<Box<dyn ChatHubIo>>>;
+
+const CONNECT_ACTION: &str = "Connect Bing Copilot ChatHub";
+
+// `locale` replaces the `_EDGE_CD` cookie of the jar
+pub(crate) fn build_chat_hub_request(
+    config: &ClientConfig,
+    url: &str,
+    cookie_jar: &CookieJar,
+    locale: Option<&Locale>,
+) -> Result<Request, BingError> {
+    let mut request = url.into_client_request()?;
+    let mut cookie_header = cookie_jar.cookie_header(&parse_url(url)?);
+    if let Some(locale) = locale {
+        cookie_header = cookie_header
+            .split("; ")
+            .filter(|pair| !pair.is_empty() && !pair.starts_with("_EDGE_CD="))
+            .map(str::to_string)
+            .chain([locale.edge_cd_cookie()])
+            .collect::<Vec<_>>()
+            .join("; ");
+    }
+    let headers = request.headers_mut();
+    headers.extend(gen_config_headers(config)?);
+    headers.insert(http::header::COOKIE, cookie_header.parse()?);
+    Ok(request)
+}
+
+fn parse_url(url: &str) -> Result<Url, BingError> {
+    Url::parse(url).map_err(|e| BingError::Config(format!("{url}: {e}")))
+}
+
+// Cookies set by the handshake response are stored in `cookie_jar`
+pub(crate) async fn connect_chat_hub(
+    config: &ClientConfig,
+    request: Request,
+    cookie_jar: &CookieJar,
+) -> Result<ChatHubStream, BingError> {
+    let url = parse_url(&request.uri().to_string())?;
+    let connect = async {
+        let uri = request.uri();
+        let scheme = uri.scheme_str().unwrap_or("wss").to_string();
+        let host = uri
+            .host()
+            .ok_or(BingError::Config(format!("No host in ChatHub url: {uri}")))?
+            .to_string();
+        let port = uri
+            .port_u16()
+            .unwrap_or(if scheme == "ws" { 80 } else { 443 });
+        let stream = open_stream(config, &scheme, &host, port).await?;
+        let connector = build_ws_connector(config)?;
+        match client_async_tls_with_config(request, stream, None, Some(connector)).await {
+            Ok((ws_stream, resp)) => {
+                cookie_jar.store_set_cookies(resp.headers().get_all(SET_COOKIE).iter(), &url);
+                Ok(ws_stream)
+            }
+            // the handshake is rejected, eg: 401 for an expired cookie
+            Err(tungstenite::Error::Http(resp)) => Err(BingError::from_status(
+                CONNECT_ACTION,
+                resp.status().as_u16(),
+                resp.body()
+                    .as_ref()
+                    .map(|body| String::from_utf8_lossy(body).into_owned())
+                    .unwrap_or_default(),
+            )),
+            Err(e) => Err(e.into()),
+        }
+    };
+    match config.connect_timeout {
+        Some(timeout) => tokio::time::timeout(timeout, connect)
+            .await
+            .map_err(|_| BingError::Timeout(CONNECT_ACTION))?,
+        None => connect.await,
+    }
+}
+
+pub(crate) async fn open_stream(
+    config: &ClientConfig,
+    scheme: &str,
+    host: &str,
+    port: u16,
+) -> Result<Box<dyn ChatHubIo>, BingError> {
+    let proxy_url = match config.proxy.url_for_scheme(scheme) {
+        Some(url) => Url::parse(url).map_err(|e| BingError::Proxy(format!("{url}: {e}")))?,
+        None => return Ok(Box::new(TcpStream::connect((host, port)).await?)),
+    };
+    let proxy_host = proxy_url.host_str().ok_or(BingError::Proxy(format!(
+        "No host in proxy url: {proxy_url}"
+    )))?;
+    let proxy_port = proxy_url.port_or_known_default().unwrap_or(1080);
+    // credentials in the proxy url take precedence over `ProxyConfig.auth`
+    let auth = if !proxy_url.username().is_empty() {
+        Some((
+            decode_userinfo(proxy_url.username())?,
+            decode_userinfo(proxy_url.password().unwrap_or_default())?,
+        ))
+    } else {
+        config
+            .proxy
+            .auth
+            .as_ref()
+            .map(|auth| (auth.username.clone(), auth.password.clone()))
+    };
+    let socket = TcpStream::connect((proxy_host, proxy_port)).await?;
+
+    match proxy_url.scheme() {
+        "http" => Ok(Box::new(http_connect(socket, host, port, auth).await?)),
+        // CONNECT over tls to the proxy, like reqwest does
+        "https" => {
+            let socket = tls_connect(config, proxy_host, socket).await?;
+            Ok(Box::new(http_connect(socket, host, port, auth).await?))
+        }
+        "socks5" => {
+            // socks5: resolve the target locally; socks5h: let the proxy resolve it
+            let target = lookup_host((host, port))
+                .await?
+                .next()
+                .ok_or(BingError::Proxy(format!("Could not resolve {host}.")))?;
+            Ok(Box::new(socks5_connect(socket, target, auth).await?))
+        }
+        "socks5h" => Ok(Box::new(socks5_connect(socket, (host, port), auth).await?)),
+        other => Err(BingError::Proxy(format!(
+            "Unsupported proxy scheme: {other}."
+        ))),
+    }
+}
+
+fn decode_userinfo(userinfo: &str) -> Result<String, BingError> {
+    urlencoding::decode(userinfo)
+        .map(|decoded| decoded.into_owned())
+        .map_err(|e| BingError::Proxy(e.to_string()))
+}
+
+async fn socks5_connect<'t, T: tokio_socks::IntoTargetAddr<'t>>(
+    socket: TcpStream,
+    target: T,
+    auth: Option<(String, String)>,
+) -> Result<Socks5Stream<TcpStream>, BingError> {
+    let stream = match auth {
+        Some((username, password)) => {
+            Socks5Stream::connect_with_password_and_socket(socket, target, &username, &password)
+                .await
+        }
+        None => Socks5Stream::connect_with_socket(socket, target).await,
+    }
+    .map_err(|e| BingError::Proxy(e.to_string()))?;
+    Ok(stream)
+}
+
+async fn http_connect<S: ChatHubIo>(
+    mut socket: S,
+    host: &str,
+    port: u16,
+    auth: Option<(String, String)>,
+) -> Result<S, BingError> {
+    let mut connect_req = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if let Some((username, password)) = auth {
+        connect_req += &format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            STANDARD.encode(format!("{username}:{password}"))
+        );
+    }
+    connect_req += "\r\n";
+    socket.write_all(connect_req.as_bytes()).await?;
+
+    // Read byte by byte so that nothing after the response head is consumed
+    let mut resp = Vec::new();
+    let mut byte = [0u8; 1];
+    while !resp.ends_with(b"\r\n\r\n") {
+        if resp.len() > 8192 {
+            return Err(BingError::Proxy("Proxy response is too long.".to_string()));
+        }
+        if socket.read(&mut byte).await? == 0 {
+            return Err(BingError::Proxy("Proxy closed the connection.".to_string()));
+        }
+        resp.push(byte[0]);
+    }
+    let resp = String::from_utf8_lossy(&resp);
+    let status_line = resp.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some("200") => Ok(socket),
+        _ => Err(BingError::Proxy(format!(
+            "Proxy CONNECT failed: {status_line}"
+        ))),
+    }
+}
+
+// Tls to an https proxy, checked the same way as the ChatHub
+async fn tls_connect(
+    config: &ClientConfig,
+    domain: &str,
+    socket: TcpStream,
+) -> Result<Box<dyn ChatHubIo>, BingError> {
+    match config.tls_mode {
+        #[cfg(feature = "rustls")]
+        TlsMode::Rustls => {
+            let connector = tokio_rustls::TlsConnector::from(std::sync::Arc::new(
+                rustls_tls::build_rustls_config(config.accept_invalid_certs),
+            ));
+            let server_name = rustls::pki_types::ServerName::try_from(domain)
+                .map_err(|e| BingError::Proxy(format!("{domain}: {e}")))?
+                .to_owned();
+            Ok(Box::new(connector.connect(server_name, socket).await?))
+        }
+        #[cfg(feature = "native-tls")]
+        TlsMode::NativeTls => {
+            let connector = native_tls::TlsConnector::builder()
+                .danger_accept_invalid_certs(config.accept_invalid_certs)
+                .build()
+                .map_err(|e| BingError::Config(e.to_string()))?;
+            let stream = tokio_native_tls::TlsConnector::from(connector)
+                .connect(domain, socket)
+                .await
+                .map_err(|e| BingError::Proxy(format!("{domain}: {e}")))?;
+            Ok(Box::new(stream))
+        }
+        #[allow(unreachable_patterns)]
+        mode => Err(BingError::Config(format!(
+            "TlsMode::{:?} is not enabled by crate features.",
+            mode
+        ))),
+    }
+}
+
+fn build_ws_connector(config: &ClientConfig) -> Result<Connector, BingError> {
+    match config.tls_mode {
+        #[cfg(feature = "rustls")]
+        TlsMode::Rustls => Ok(Connector::Rustls(std::sync::Arc::new(
+            rustls_tls::build_rustls_config(config.accept_invalid_certs),
+        ))),
+        #[cfg(feature = "native-tls")]
+        TlsMode::NativeTls => Ok(Connector::NativeTls(
+            native_tls::TlsConnector::builder()
+                .danger_accept_invalid_certs(config.accept_invalid_certs)
+                .build()
+                .map_err(|e| BingError::Config(e.to_string()))?,
+        )),
+        #[allow(unreachable_patterns)]
+        mode => Err(BingError::Config(format!(
+            "TlsMode::{:?} is not enabled by crate features.",
+            mode
+        ))),
+    }
+}
+
+#[cfg(feature = "rustls")]
+mod rustls_tls {
+    use std::sync::Arc;
+
+    use rustls::{
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{ring::default_provider, verify_tls12_signature, verify_tls13_signature},
+        pki_types::{CertificateDer, ServerName, UnixTime},
+        ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    };
+
+    pub(super) fn build_rustls_config(accept_invalid_certs: bool) -> ClientConfig {
+        if accept_invalid_certs {
+            ClientConfig::builder()
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoCertVerification))
+                .with_no_client_auth()
+        } else {
+            let mut root_store = RootCertStore::empty();
+            root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            ClientConfig::builder()
+                .with_root_certificates(root_store)
+                .with_no_client_auth()
+        }
+    }
+
+    // Same behaviour as reqwest's `danger_accept_invalid_certs`: skip the certificate check,
+    // but still verify handshake signatures.
+    #[derive(Debug)]
+    struct NoCertVerification;
+
+    impl ServerCertVerifier for NoCertVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            verify_tls12_signature(
+                message,
+                cert,
+                dss,
+                &default_provider().signature_verification_algorithms,
+            )
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            verify_tls13_signature(
+                message,
+                cert,
+                dss,
+                &default_provider().signature_verification_algorithms,
+            )
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            default_provider()
+                .signature_verification_algorithms
+                .supported_schemes()
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use chrono::DateTime;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::types::error_type::BingError;
+
+use super::{
+    cookie_jar::{shared_domain, JsonCookie},
+    cookie_pre::domain_fits,
+};
+
+// Lines like "domain\tinclude_subdomains\tpath\tsecure\texpiry\tname\tvalue", as written by curl and wget
+pub(crate) fn parse_netscape(text: &str, www: &Url) -> Result<Vec<JsonCookie>, BingError> {
+    let mut cookies = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 7 {
+            return Err(BingError::Cookie(format!(
+                "Line {} of the Netscape cookies has {} fields instead of 7.",
+                index + 1,
+                fields.len()
+            )));
+        }
+        let expiry = fields[4].trim().parse::<f64>().unwrap_or(0.0);
+        cookies.push(JsonCookie {
+            domain: fields[0].to_string(),
+            expiration_date: (expiry > 0.0).then_some(expiry),
+            host_only: !fields[1].eq_ignore_ascii_case("TRUE"),
+            http_only,
+            name: fields[5].to_string(),
+            path: fields[2].to_string(),
+            same_site: None,
+            secure: fields[3].eq_ignore_ascii_case("TRUE"),
+            session: expiry <= 0.0,
+            store_id: None,
+            // the value may contain tabs
+            value: fields[6..].join("\t"),
+        });
+    }
+    Ok(filter_cookies(cookies, www))
+}
+
+pub(crate) fn to_netscape(cookies: &[JsonCookie]) -> String {
+    let mut text = String::from("# Netscape HTTP Cookie File\n");
+    for cookie in cookies {
+        let domain = if cookie.host_only {
+            cookie.domain.trim_start_matches('.').to_string()
+        } else {
+            format!(".{}", cookie.domain.trim_start_matches('.'))
+        };
+        text.push_str(&format!(
+            "{}{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            if cookie.http_only { "#HttpOnly_" } else { "" },
+            domain,
+            if cookie.host_only { "FALSE" } else { "TRUE" },
+            cookie.path,
+            if cookie.secure { "TRUE" } else { "FALSE" },
+            cookie.expiration_date.map_or(0, |expiry| expiry as i64),
+            cookie.name,
+            cookie.value
+        ));
+    }
+    text
+}
+
+#[derive(Deserialize)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Deserialize)]
+struct HarLog {
+    #[serde(default)]
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Deserialize)]
+struct HarEntry {
+    request: HarRequest,
+    response: Option<HarResponse>,
+}
+
+#[derive(Deserialize)]
+struct HarRequest {
+    url: String,
+    #[serde(default)]
+    cookies: Vec<HarCookie>,
+}
+
+#[derive(Deserialize)]
+struct HarResponse {
+    #[serde(default)]
+    cookies: Vec<HarCookie>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarCookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    expires: Option<String>,
+    #[serde(default)]
+    http_only: bool,
+    #[serde(default)]
+    secure: bool,
+}
+
+// Cookies sent with and set by the requests in the HAR, later entries win
+pub(crate) fn parse_har(text: &str, www: &Url) -> Result<Vec<JsonCookie>, BingError> {
+    let har: Har = serde_json::from_str(text)?;
+    let mut cookies: Vec<JsonCookie> = Vec::new();
+    for entry in har.log.entries {
+        let Ok(url) = Url::parse(&entry.request.url) else {
+            continue;
+        };
+        let Some(host) = url.host_str() else {
+            continue;
+        };
+        // only the name and value are sent, so the cookie is shared like `Cookie::HeadStr`
+        let request_domain = shared_domain(&url).unwrap_or_else(|| host.to_string());
+        let request_cookies = entry.request.cookies.into_iter().map(|cookie| JsonCookie {
+            domain: request_domain.clone(),
+            expiration_date: None,
+            host_only: false,
+            http_only: false,
+            name: cookie.name,
+            path: "/".to_string(),
+            same_site: None,
+            secure: false,
+            session: true,
+            store_id: None,
+            value: cookie.value,
+        });
+        let response_cookies = entry
+            .response
+            .map(|response| response.cookies)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|cookie| {
+                let expiration_date = cookie
+                    .expires
+                    .as_deref()
+                    .and_then(|expires| DateTime::parse_from_rfc3339(expires).ok())
+                    .map(|expires| expires.timestamp() as f64);
+                JsonCookie {
+                    host_only: cookie.domain.is_none(),
+                    domain: cookie.domain.unwrap_or_else(|| host.to_string()),
+                    session: expiration_date.is_none(),
+                    expiration_date,
+                    http_only: cookie.http_only,
+                    name: cookie.name,
+                    path: cookie.path.unwrap_or_else(|| "/".to_string()),
+                    same_site: None,
+                    secure: cookie.secure,
+                    store_id: None,
+                    value: cookie.value,
+                }
+            });
+        for cookie in request_cookies.chain(response_cookies) {
+            cookies.retain(|old| {
+                !(old.name == cookie.name
+                    && old.path == cookie.path
+                    && old.domain.trim_start_matches('.') == cookie.domain.trim_start_matches('.'))
+            });
+            cookies.push(cookie);
+        }
+    }
+    Ok(filter_cookies(cookies, www))
+}
+
+fn filter_cookies(mut cookies: Vec<JsonCookie>, www: &Url) -> Vec<JsonCookie> {
+    cookies.retain(|cookie| domain_fits(&cookie.domain, www));
+    cookies
+}
+
+#[cfg(feature = "browser-cookies")]
+mod browser {
+    use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+    use reqwest::Url;
+    use rusqlite::{Connection, OpenFlags};
+
+    use crate::{
+        types::error_type::BingError,
+        utils::{cookie_jar::JsonCookie, cookie_pre::domain_fits},
+    };
+
+    use super::filter_cookies;
+
+    // Seconds between 1601-01-01 and 1970-01-01
+    const WINDOWS_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+    fn sqlite_error(e: rusqlite::Error) -> BingError {
+        BingError::Cookie(format!("Failed to read the cookie database; {e}"))
+    }
+
+    // `immutable` lets us read the database while the browser holds its lock
+    fn open_database(path: &str) -> Result<Connection, BingError> {
+        let path = path
+            .replace('%', "%25")
+            .replace('?', "%3f")
+            .replace('#', "%23");
+        Connection::open_with_flags(
+            format!("file:{path}?immutable=1"),
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+        )
+        .map_err(sqlite_error)
+    }
+
+    fn same_site(value: i64) -> Option<String> {
+        match value {
+            0 => Some("no_restriction".to_string()),
+            1 => Some("lax".to_string()),
+            2 => Some("strict".to_string()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn read_firefox_db(path: &str, www: &Url) -> Result<Vec<JsonCookie>, BingError> {
+        let connection = open_database(path)?;
+        let mut statement = connection
+            .prepare(
+                "SELECT host, name, value, path, expiry, isSecure, isHttpOnly, sameSite FROM moz_cookies",
+            )
+            .map_err(sqlite_error)?;
+        let cookies = statement
+            .query_map([], |row| {
+                let host: String = row.get(0)?;
+                let mut expiry: i64 = row.get(4)?;
+                // newer Firefox versions store milliseconds
+                if expiry > 100_000_000_000 {
+                    expiry /= 1000;
+                }
+                Ok(JsonCookie {
+                    host_only: !host.starts_with('.'),
+                    domain: host,
+                    expiration_date: (expiry > 0).then_some(expiry as f64),
+                    http_only: row.get::<_, i64>(6)? != 0,
+                    name: row.get(1)?,
+                    path: row.get(3)?,
+                    same_site: same_site(row.get(7)?),
+                    secure: row.get::<_, i64>(5)? != 0,
+                    session: expiry <= 0,
+                    store_id: None,
+                    value: row.get(2)?,
+                })
+            })
+            .map_err(sqlite_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(sqlite_error)?;
+        Ok(filter_cookies(cookies, www))
+    }
+
+    pub(crate) fn read_chromium_db(
+        path: &str,
+        password: Option<&str>,
+        www: &Url,
+    ) -> Result<Vec<JsonCookie>, BingError> {
+        let connection = open_database(path)?;
+        let version: i64 = connection
+            .query_row("SELECT value FROM meta WHERE key = 'version'", [], |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(sqlite_error)?
+            .parse()
+            .unwrap_or(0);
+        let mut statement = connection
+            .prepare(
+                "SELECT host_key, name, value, encrypted_value, path, expires_utc, is_secure, is_httponly, samesite FROM cookies",
+            )
+            .map_err(sqlite_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Vec<u8>>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, i64>(5)?,
+                    row.get::<_, i64>(6)?,
+                    row.get::<_, i64>(7)?,
+                    row.get::<_, i64>(8)?,
+                ))
+            })
+            .map_err(sqlite_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(sqlite_error)?;
+
+        let mut cookies = Vec::new();
+        for (host, name, value, encrypted_value, path, expires_utc, secure, http_only, samesite) in
+            rows
+        {
+            // only the bing cookies are decrypted
+            if !domain_fits(&host, www) {
+                continue;
+            }
+            let mut cookie = JsonCookie {
+                host_only: !host.starts_with('.'),
+                domain: host,
+                expiration_date: (expires_utc > 0)
+                    .then(|| (expires_utc / 1_000_000 - WINDOWS_EPOCH_OFFSET) as f64),
+                http_only: http_only != 0,
+                name,
+                path,
+                same_site: same_site(samesite),
+                secure: secure != 0,
+                session: expires_utc <= 0,
+                store_id: None,
+                value,
+            };
+            if !encrypted_value.is_empty() {
+                cookie.value = decrypt_value(&encrypted_value, password, version)?;
+            }
+            cookies.push(cookie);
+        }
+        Ok(cookies)
+    }
+
+    // "v10" values use the built-in "peanuts" password, "v11" ones the secret of the keyring
+    pub(crate) fn decrypt_value(
+        encrypted_value: &[u8],
+        password: Option<&str>,
+        version: i64,
+    ) -> Result<String, BingError> {
+        let (password, data) =
+            match encrypted_value.split_at(3.min(encrypted_value.len())) {
+                (b"v10", data) => ("peanuts", data),
+                (b"v11", data) => match password {
+                    Some(password) => (password, data),
+                    None => return Err(BingError::Cookie(
+                        "The cookies are encrypted with the keyring secret, pass it as `password`."
+                            .to_string(),
+                    )),
+                },
+                _ => {
+                    return Err(BingError::Cookie(
+                        "Unsupported encryption of the Chromium cookies.".to_string(),
+                    ))
+                }
+            };
+        let key = pbkdf2::pbkdf2_hmac_array::<sha1::Sha1, 16>(password.as_bytes(), b"saltysalt", 1);
+        let decrypted = cbc::Decryptor::<aes::Aes128>::new(&key.into(), &[b' '; 16].into())
+            .decrypt_padded_vec_mut::<Pkcs7>(data)
+            .map_err(|_| {
+                BingError::Cookie("Failed to decrypt the Chromium cookies.".to_string())
+            })?;
+        // since version 24 the value is prefixed by the sha256 of the host
+        let decrypted = if version >= 24 && decrypted.len() >= 32 {
+            &decrypted[32..]
+        } else {
+            &decrypted[..]
+        };
+        String::from_utf8(decrypted.to_vec())
+            .map_err(|e| BingError::Cookie(format!("Failed to decrypt the Chromium cookies; {e}")))
+    }
+}
+
+#[cfg(feature = "browser-cookies")]
+pub(crate) use browser::{read_chromium_db, read_firefox_db};
+
+#[cfg(not(feature = "browser-cookies"))]
+pub(crate) fn read_firefox_db(_path: &str, _www: &Url) -> Result<Vec<JsonCookie>, BingError> {
+    Err(browser_cookies_disabled())
+}
+
+#[cfg(not(feature = "browser-cookies"))]
+pub(crate) fn read_chromium_db(
+    _path: &str,
+    _password: Option<&str>,
+    _www: &Url,
+) -> Result<Vec<JsonCookie>, BingError> {
+    Err(browser_cookies_disabled())
+}
+
+#[cfg(not(feature = "browser-cookies"))]
+fn browser_cookies_disabled() -> BingError {
+    BingError::Config(
+        "Reading browser cookie databases needs the `browser-cookies` feature.".to_string(),
+    )
+}
@@ -0,0 +1,274 @@
+use std::sync::RwLock;
+
+use cookie::{time::OffsetDateTime, Cookie as RawCookie, SameSite};
+use cookie_store::{CookieDomain, CookieExpiration, CookieStore};
+use reqwest::{header::HeaderValue, Url};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::types::{
+    client_config_type::Endpoints, cookie_report_type::ExpiredCookie, error_type::BingError,
+};
+
+// The format of `Cookie::JsonPath` and `Cookie::JsonStr`, as exported by `Cookie Editor`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonCookie {
+    #[serde(default)]
+    pub domain: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration_date: Option<f64>,
+    #[serde(default)]
+    pub host_only: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    pub name: String,
+    #[serde(default = "default_path")]
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_site: Option<String>,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub session: bool,
+    #[serde(default)]
+    pub store_id: Option<String>,
+    pub value: String,
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+// Cookies of a client, updated by the `Set-Cookie` headers of every response
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    store: RwLock<CookieStore>,
+}
+
+impl CookieJar {
+    pub(crate) fn from_json_cookies(cookies: Vec<JsonCookie>) -> CookieJar {
+        let jar = CookieJar::default();
+        {
+            let mut store = jar.store.write().unwrap();
+            for cookie in cookies {
+                let domain = cookie.domain.trim_start_matches('.');
+                let Ok(url) = Url::parse(&format!("https://{}{}", domain, cookie.path)) else {
+                    continue;
+                };
+                let mut raw = RawCookie::build(cookie.name, cookie.value)
+                    .path(cookie.path)
+                    .secure(cookie.secure)
+                    .http_only(cookie.http_only)
+                    .finish();
+                if !cookie.host_only {
+                    raw.set_domain(domain.to_string());
+                }
+                if let Some(same_site) = cookie.same_site.as_deref().and_then(parse_same_site) {
+                    raw.set_same_site(same_site);
+                }
+                if let (Some(expiration_date), false) = (cookie.expiration_date, cookie.session) {
+                    if let Ok(expires) = OffsetDateTime::from_unix_timestamp(expiration_date as i64)
+                    {
+                        raw.set_expires(expires);
+                    }
+                }
+                // expired or invalid cookies are dropped
+                let _ = store.insert_raw(&raw, &url);
+            }
+        }
+        jar
+    }
+
+    // `header` is like "a=1;b=2", the cookies are sent to every host under the domain of `endpoints.www`
+    pub(crate) fn from_header_str(
+        header: &str,
+        endpoints: &Endpoints,
+    ) -> Result<CookieJar, BingError> {
+        let jar = CookieJar::default();
+        for pair in header.split(';') {
+            jar.insert_pair(pair, endpoints)?;
+        }
+        Ok(jar)
+    }
+
+    // Inserts a "name=value" session cookie, eg: `_EDGE_CD`
+    pub(crate) fn insert_pair(&self, pair: &str, endpoints: &Endpoints) -> Result<(), BingError> {
+        let Some((name, value)) = pair.trim().split_once('=') else {
+            return Ok(());
+        };
+        let url = Url::parse(&endpoints.www).map_err(|e| BingError::Config(e.to_string()))?;
+        let mut raw = RawCookie::build(name.trim().to_string(), value.trim().to_string())
+            .path("/")
+            .finish();
+        if let Some(domain) = shared_domain(&url) {
+            raw.set_domain(domain);
+        }
+        self.store
+            .write()
+            .unwrap()
+            .insert_raw(&raw, &url)
+            .map_err(|e| BingError::Cookie(e.to_string()))?;
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.store
+            .read()
+            .unwrap()
+            .iter_unexpired()
+            .any(|cookie| cookie.name() == name)
+    }
+
+    // The `Cookie` header for `url`
+    pub fn cookie_header(&self, url: &Url) -> String {
+        self.store
+            .read()
+            .unwrap()
+            .get_request_values(&http_url(url))
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    pub(crate) fn store_set_cookies<'a>(
+        &self,
+        cookie_headers: impl Iterator<Item = &'a HeaderValue>,
+        url: &Url,
+    ) {
+        let cookies = cookie_headers
+            .filter_map(|header| header.to_str().ok())
+            .filter_map(|header| RawCookie::parse(header.to_string()).ok());
+        self.store
+            .write()
+            .unwrap()
+            .store_response_cookies(cookies, &http_url(url));
+    }
+
+    // Cookies that expired while they were in the jar, they are no longer sent
+    pub(crate) fn expired_cookies(&self) -> Vec<ExpiredCookie> {
+        self.store
+            .read()
+            .unwrap()
+            .iter_any()
+            .filter(|cookie| cookie.is_expired())
+            .map(|cookie| ExpiredCookie {
+                name: cookie.name().to_string(),
+                domain: json_domain(&cookie.domain).0,
+                expiration_date: expiration_date(&cookie.expires).unwrap_or_default(),
+                required: false,
+            })
+            .collect()
+    }
+
+    pub fn to_json_cookies(&self) -> Vec<JsonCookie> {
+        self.store
+            .read()
+            .unwrap()
+            .iter_unexpired()
+            .map(|cookie| {
+                let (domain, host_only) = json_domain(&cookie.domain);
+                let expiration_date = expiration_date(&cookie.expires);
+                JsonCookie {
+                    domain,
+                    session: expiration_date.is_none(),
+                    expiration_date,
+                    host_only,
+                    http_only: cookie.http_only().unwrap_or(false),
+                    name: cookie.name().to_string(),
+                    path: cookie.path.as_ref().to_string(),
+                    same_site: cookie.same_site().map(|same_site| {
+                        match same_site {
+                            SameSite::Strict => "strict",
+                            SameSite::Lax => "lax",
+                            SameSite::None => "no_restriction",
+                        }
+                        .to_string()
+                    }),
+                    secure: cookie.secure().unwrap_or(false),
+                    store_id: None,
+                    value: cookie.value().to_string(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        self.store_set_cookies(cookie_headers, url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let header = self.cookie_header(url);
+        if header.is_empty() {
+            None
+        } else {
+            HeaderValue::from_str(&header).ok()
+        }
+    }
+}
+
+impl Serialize for CookieJar {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json_cookies().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CookieJar {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(CookieJar::from_json_cookies(Vec::deserialize(deserializer)?))
+    }
+}
+
+// Websocket urls are matched like http ones, otherwise secure cookies are never sent
+fn http_url(url: &Url) -> Url {
+    let mut url = url.clone();
+    match url.scheme() {
+        "wss" => {
+            let _ = url.set_scheme("https");
+        }
+        "ws" => {
+            let _ = url.set_scheme("http");
+        }
+        _ => {}
+    }
+    url
+}
+
+// The `domain` and `hostOnly` of a `JsonCookie`
+fn json_domain(domain: &CookieDomain) -> (String, bool) {
+    match domain {
+        CookieDomain::HostOnly(domain) => (domain.clone(), true),
+        CookieDomain::Suffix(domain) => (format!(".{domain}"), false),
+        _ => (String::new(), true),
+    }
+}
+
+// unix timestamp in seconds, `None` for a session cookie
+fn expiration_date(expires: &CookieExpiration) -> Option<f64> {
+    match expires {
+        CookieExpiration::AtUtc(expires) => {
+            Some(expires.unix_timestamp_nanos() as f64 / 1_000_000_000.0)
+        }
+        CookieExpiration::SessionEnd => None,
+    }
+}
+
+// "www.bing.com" -> "bing.com", so that "sydney.bing.com" gets the cookies too
+pub(crate) fn shared_domain(url: &Url) -> Option<String> {
+    let host = url.domain()?;
+    match host.split_once('.') {
+        Some((_, rest)) if rest.contains('.') => Some(rest.to_string()),
+        Some(_) => Some(host.to_string()),
+        None => None,
+    }
+}
+
+fn parse_same_site(same_site: &str) -> Option<SameSite> {
+    match same_site {
+        "strict" => Some(SameSite::Strict),
+        "lax" => Some(SameSite::Lax),
+        "no_restriction" | "none" => Some(SameSite::None),
+        _ => None,
+    }
+}
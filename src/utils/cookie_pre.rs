@@ -2,7 +2,20 @@ use serde::Deserialize;
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
 
-use crate::types::cookie_type::Cookie as CookieInput;
+use chrono::Utc;
+use reqwest::Url;
+
+use crate::types::{
+    client_config_type::ClientConfig,
+    cookie_report_type::{CookieCheck, CookieReport, ExpiredCookie, ForeignCookie},
+    cookie_type::Cookie as CookieInput,
+    error_type::BingError,
+};
+
+use super::{
+    cookie_import::{parse_har, parse_netscape, read_chromium_db, read_firefox_db},
+    cookie_jar::{shared_domain, CookieJar, JsonCookie},
+};
 
 // 定义一个结构体来表示Cookie
 #[derive(Deserialize)]
@@ -11,7 +24,7 @@ struct Cookie {
     pub value: String,
 }
 
-pub async fn file_cookie2str(file_path: &str) -> Result<String, anyhow::Error> {
+pub async fn file_cookie2str(file_path: &str) -> Result<String, BingError> {
     // 异步地从文件中读取JSON
     let mut file = File::open(file_path).await?;
     let mut contents = String::new();
@@ -21,9 +34,9 @@ pub async fn file_cookie2str(file_path: &str) -> Result<String, anyhow::Error> {
     cookie2str(&contents).await
 }
 
-pub async fn cookie2str(json_cookie: &str) -> Result<String, anyhow::Error> {
+pub async fn cookie2str(json_cookie: &str) -> Result<String, BingError> {
     // 将JSON转换为Value对象
-    let cookies: Vec<Cookie> = serde_json::from_str(&json_cookie)?;
+    let cookies: Vec<Cookie> = serde_json::from_str(json_cookie)?;
     let cookie_string: String = cookies
         .iter()
         .map(|cookie| format!("{}={}", cookie.name, cookie.value))
@@ -33,25 +46,118 @@ pub async fn cookie2str(json_cookie: &str) -> Result<String, anyhow::Error> {
     Ok(cookie_string)
 }
 
-pub async fn parse_cookie(cookie: &CookieInput) -> Result<String, anyhow::Error> {
-    let mut cookie_str = match cookie {
-        CookieInput::JsonPath(path) => file_cookie2str(&path).await,
-        CookieInput::JsonStr(json) => cookie2str(&json).await,
-        CookieInput::HeadPath(path) => {
-            let mut file = File::open(path).await?;
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).await?;
-            Ok(contents)
+pub(crate) async fn load_cookie_jar(
+    cookie: &CookieInput,
+    config: &ClientConfig,
+) -> Result<(CookieJar, CookieReport), BingError> {
+    let endpoints = &config.endpoints;
+    let www = Url::parse(&endpoints.www).map_err(|e| BingError::Config(e.to_string()))?;
+    let json_cookies = match cookie {
+        CookieInput::JsonPath(path) => Some(serde_json::from_str::<Vec<JsonCookie>>(
+            &tokio::fs::read_to_string(path).await?,
+        )?),
+        CookieInput::JsonStr(json) => Some(serde_json::from_str::<Vec<JsonCookie>>(json)?),
+        CookieInput::HeadPath(_) | CookieInput::HeadStr(_) => None,
+        CookieInput::NetscapePath(path) => Some(parse_netscape(
+            &tokio::fs::read_to_string(path).await?,
+            &www,
+        )?),
+        CookieInput::NetscapeStr(text) => Some(parse_netscape(text, &www)?),
+        CookieInput::HarPath(path) => {
+            Some(parse_har(&tokio::fs::read_to_string(path).await?, &www)?)
+        }
+        // sqlite is blocking
+        CookieInput::FirefoxDb(path) => {
+            let (path, www) = (path.clone(), www.clone());
+            Some(tokio::task::spawn_blocking(move || read_firefox_db(&path, &www)).await??)
+        }
+        CookieInput::ChromiumDb { path, password } => {
+            let (path, password, www) = (path.clone(), password.clone(), www.clone());
+            Some(
+                tokio::task::spawn_blocking(move || {
+                    read_chromium_db(&path, password.as_deref(), &www)
+                })
+                .await??,
+            )
         }
-        CookieInput::HeadStr(cookie) => Ok(cookie.to_owned()),
     };
-    if let Ok(cookie_str) = cookie_str.as_mut(){
-        if !cookie_str.contains("_EDGE_CD"){
-            if !cookie_str.ends_with(";"){
-                *cookie_str += ";"
-            }
-            *cookie_str += "_EDGE_CD=m=en-us&u=en-us"
+    let (jar, mut report) = match json_cookies {
+        Some(mut json_cookies) => {
+            let report = check_json_cookies(&json_cookies, config);
+            // the jar is shared with the client that follows redirects to any host
+            json_cookies.retain(|cookie| domain_fits(&cookie.domain, &www));
+            (CookieJar::from_json_cookies(json_cookies), report)
+        }
+        None => {
+            let header = match cookie {
+                CookieInput::HeadPath(path) => tokio::fs::read_to_string(path).await?,
+                CookieInput::HeadStr(header) => header.to_owned(),
+                _ => unreachable!(),
+            };
+            (
+                CookieJar::from_header_str(&header, endpoints)?,
+                CookieReport::default(),
+            )
         }
     };
-    cookie_str
+    if config.cookie_check != CookieCheck::Off {
+        report.missing = config
+            .required_cookies
+            .iter()
+            .filter(|name| {
+                !jar.contains(name) && !report.expired.iter().any(|cookie| &&cookie.name == name)
+            })
+            .cloned()
+            .collect();
+    }
+    if config.cookie_check == CookieCheck::Strict && !report.is_valid() {
+        return Err(BingError::InvalidCookies(Box::new(report)));
+    }
+    if !jar.contains("_EDGE_CD") {
+        jar.insert_pair(&config.locale.edge_cd_cookie(), endpoints)?;
+    }
+    Ok((jar, report))
+}
+
+fn check_json_cookies(cookies: &[JsonCookie], config: &ClientConfig) -> CookieReport {
+    let mut report = CookieReport::default();
+    if config.cookie_check == CookieCheck::Off {
+        return report;
+    }
+    let now = Utc::now().timestamp() as f64;
+    let www = Url::parse(&config.endpoints.www).ok();
+    for cookie in cookies {
+        if let (Some(expiration_date), false) = (cookie.expiration_date, cookie.session) {
+            if expiration_date <= now {
+                report.expired.push(ExpiredCookie {
+                    name: cookie.name.clone(),
+                    domain: cookie.domain.clone(),
+                    expiration_date,
+                    required: config.required_cookies.contains(&cookie.name),
+                });
+            }
+        }
+        if let Some(www) = &www {
+            if !domain_fits(&cookie.domain, www) {
+                report.foreign.push(ForeignCookie {
+                    name: cookie.name.clone(),
+                    domain: cookie.domain.clone(),
+                });
+            }
+        }
+    }
+    report
+}
+
+// eg: ".bing.com", "www.bing.com" and "sydney.bing.com" fit "https://www.bing.com"
+pub(crate) fn domain_fits(domain: &str, www: &Url) -> bool {
+    let domain = domain.trim_start_matches('.').to_lowercase();
+    let host = www.host_str().unwrap_or_default().to_lowercase();
+    if domain.is_empty() || domain == host || host.ends_with(&format!(".{domain}")) {
+        return true;
+    }
+    match shared_domain(www) {
+        Some(shared) => domain == shared || domain.ends_with(&format!(".{shared}")),
+        None => false,
+    }
 }
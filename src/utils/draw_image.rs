@@ -3,44 +3,45 @@ use std::time::Duration;
 use chrono::{DateTime, Utc};
 use reqwest::{
     header::{HeaderMap, CONTENT_SECURITY_POLICY},
-    redirect::Policy,
+    Client as ReqwestClient,
 };
 use serde_json::{json, Value};
-use tokio::time::sleep;
+use tokio::time::{sleep, Instant};
 
 use crate::{
     const_vars::{gen_draw_image_url, gen_get_images_url},
-    types::bot_easy_resp_type::Image,
+    types::{bot_easy_resp_type::Image, client_config_type::Endpoints, error_type::BingError},
 };
 
+use super::http_client::check_status;
+
+const DRAW_ACTION: &str = "Draw Image";
+// The first poll waits this long, every next one twice as long up to `POLL_MAX_INTERVAL`
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const POLL_MAX_INTERVAL: Duration = Duration::from_secs(5);
+
+// `client` must not follow redirects, the request id is read from the `Location` headers
 pub async fn gen_pool_image_url(
+    client: &ReqwestClient,
+    endpoints: &Endpoints,
     prompt: &str,
     reqwest_header: HeaderMap,
     message_id: &str,
-) -> Result<String, anyhow::Error> {
-    #[cfg(not(feature = "allow-invalid-tls"))]
-    let client = reqwest::Client::builder()
-        .redirect(Policy::none())
-        .build()?;
-
-    #[cfg(feature = "allow-invalid-tls")]
-    let client = reqwest::Client::builder()
-        .redirect(Policy::none())
-        .danger_accept_invalid_certs(true)
-        .build()?;
-
+) -> Result<String, BingError> {
     let response = client
-        .get(gen_draw_image_url(prompt, &message_id))
+        .get(gen_draw_image_url(endpoints, prompt, message_id))
         .headers(reqwest_header.clone())
         .send()
         .await?;
     let redirect_url = response
         .headers()
         .get("location")
-        .ok_or(anyhow::anyhow!("Drawing Failed: Redirect failed"))?
-        .to_str()
-        .map_err(|_| anyhow::anyhow!("Drawing Failed: Invalid location header"))?;
-    let redirect_url = format!("https://www.bing.com{}", redirect_url);
+        .ok_or(BingError::MissingHeader {
+            action: DRAW_ACTION,
+            header: "Location",
+        })?
+        .to_str()?;
+    let redirect_url = format!("{}{}", endpoints.www, redirect_url);
 
     let response = client
         .get(&redirect_url)
@@ -50,54 +51,45 @@ pub async fn gen_pool_image_url(
     let mut request_id = response
         .headers()
         .get("location")
-        .ok_or(anyhow::anyhow!("Drawing Failed: Redirect failed"))?
-        .to_str()
-        .map_err(|_| anyhow::anyhow!("Drawing Failed: Invalid location header"))?
+        .ok_or(BingError::MissingHeader {
+            action: DRAW_ACTION,
+            header: "Location",
+        })?
+        .to_str()?
         .split("id=")
         .last()
-        .ok_or(anyhow::anyhow!("Drawing Failed: Invalid location header"))?;
-    request_id = &request_id.split('&').collect::<Vec<&str>>()[0];
-    Ok(gen_get_images_url(request_id))
+        .ok_or(BingError::Draw("Invalid location header".to_string()))?;
+    request_id = request_id.split('&').collect::<Vec<&str>>()[0];
+    Ok(gen_get_images_url(endpoints, request_id))
 }
 
 pub async fn poll_images(
-    polling_url: String,
-    reqwest_header: HeaderMap,
+    client: &ReqwestClient,
+    polling_url: &str,
     wait_long: bool,
-) -> Result<Vec<Image>, anyhow::Error> {
-    #[cfg(not(feature = "allow-invalid-tls"))]
-    let client = reqwest::Client::builder()
-        .default_headers(reqwest_header)
-        .build()?;
-
-    #[cfg(feature = "allow-invalid-tls")]
-    let client = reqwest::Client::builder()
-        .default_headers(reqwest_header)
-        .danger_accept_invalid_certs(true)
-        .build()?;
-
-    let mut times = match wait_long {
-        true => 100,
-        _ => 50,
-    };
+) -> Result<Vec<Image>, BingError> {
+    // a new drawing takes longer than one of the history
+    let deadline = Instant::now()
+        + match wait_long {
+            true => Duration::from_secs(180),
+            _ => Duration::from_secs(60),
+        };
+    let mut interval = POLL_INTERVAL;
     let content = loop {
-        times -= 1;
-        if times < 0 {
-            return Err(anyhow::anyhow!("Drawing Failed: Timed out."));
-        }
         let response = client
-            .get(&polling_url)
+            .get(polling_url)
             .header(CONTENT_SECURITY_POLICY, "script-src 'none'")
             .send()
             .await?;
-        if response.status() != 200 {
-            return Err(anyhow::anyhow!("Drawing Failed: Could not get results"));
-        }
-        let text = response.text().await?;
+        let text = check_status(DRAW_ACTION, response).await?.text().await?;
         if text.contains("th.bing.com/th") {
             break text;
         }
-        sleep(Duration::from_micros(500)).await;
+        if Instant::now() + interval > deadline {
+            return Err(BingError::Timeout(DRAW_ACTION));
+        }
+        sleep(interval).await;
+        interval = (interval * 2).min(POLL_MAX_INTERVAL);
     };
     let links = content
         .split("src=\"")
@@ -127,16 +119,14 @@ pub async fn poll_images(
             .collect();
         Ok(imgs)
     } else {
-        return Err(anyhow::anyhow!(
-            "Poll Draw Image Failed: No images are found."
-        ));
+        Err(BingError::Draw("No images are found.".to_string()))
     }
 }
 
 pub fn gen_update_draw_conversation(message_id: &str, prompt: &str, persistent_url: &str) -> Value {
     let time = {
         let dt: DateTime<Utc> = Utc::now();
-        format!("{}", dt.to_rfc3339())
+        dt.to_rfc3339()
     };
     json!({
       "author": "bot",
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT},
+    redirect::Policy,
+    Client as ReqwestClient, Proxy, Response,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::types::{
+    client_config_type::{ClientConfig, ProxyConfig, TlsMode},
+    error_type::BingError,
+    Result as ServiceResult,
+};
+
+use super::cookie_jar::CookieJar;
+
+#[derive(Deserialize)]
+struct ServiceResp {
+    result: ServiceResult,
+}
+
+// Headers shared by every request of a client: user agent and the extra headers from config
+pub(crate) fn gen_config_headers(config: &ClientConfig) -> Result<HeaderMap, BingError> {
+    let mut headers = HeaderMap::new();
+    if let Some(user_agent) = &config.user_agent {
+        headers.insert(USER_AGENT, HeaderValue::from_str(user_agent)?);
+    }
+    for (name, value) in &config.headers {
+        headers.insert(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_str(value)?,
+        );
+    }
+    Ok(headers)
+}
+
+pub(crate) fn build_reqwest_client(
+    config: &ClientConfig,
+    mut headers: HeaderMap,
+    follow_redirect: bool,
+    cookie_jar: Option<Arc<CookieJar>>,
+) -> Result<ReqwestClient, BingError> {
+    headers.extend(gen_config_headers(config)?);
+    let mut builder = ReqwestClient::builder()
+        .default_headers(headers)
+        .danger_accept_invalid_certs(config.accept_invalid_certs);
+    if let Some(cookie_jar) = cookie_jar {
+        builder = builder.cookie_provider(cookie_jar);
+    }
+    if !follow_redirect {
+        builder = builder.redirect(Policy::none());
+    }
+    if let Some(timeout) = config.request_timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(timeout) = config.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    // reqwest uses the first matching proxy, so the per-scheme ones go first
+    if let Some(url) = &config.proxy.http {
+        builder = builder.proxy(with_proxy_auth(
+            Proxy::http(url).map_err(proxy_error)?,
+            &config.proxy,
+        ));
+    }
+    if let Some(url) = &config.proxy.https {
+        builder = builder.proxy(with_proxy_auth(
+            Proxy::https(url).map_err(proxy_error)?,
+            &config.proxy,
+        ));
+    }
+    if let Some(url) = &config.proxy.all {
+        builder = builder.proxy(with_proxy_auth(
+            Proxy::all(url).map_err(proxy_error)?,
+            &config.proxy,
+        ));
+    }
+    builder = match config.tls_mode {
+        #[cfg(feature = "rustls")]
+        TlsMode::Rustls => builder.use_rustls_tls(),
+        #[cfg(feature = "native-tls")]
+        TlsMode::NativeTls => builder.use_native_tls(),
+        #[allow(unreachable_patterns)]
+        mode => {
+            return Err(BingError::Config(format!(
+                "TlsMode::{:?} is not enabled by crate features.",
+                mode
+            )))
+        }
+    };
+    Ok(builder.build()?)
+}
+
+// Responses that are not 2xx are turned into errors, eg: 401 -> `BingError::Unauthorized`
+pub(crate) async fn check_status(
+    action: &'static str,
+    resp: Response,
+) -> Result<Response, BingError> {
+    if resp.status().is_success() {
+        return Ok(resp);
+    }
+    let status = resp.status().as_u16();
+    let body = resp.text().await.unwrap_or_default();
+    Err(BingError::from_status(action, status, body))
+}
+
+pub(crate) async fn json_resp<T: DeserializeOwned>(
+    action: &'static str,
+    resp: Response,
+) -> Result<T, BingError> {
+    let body = check_status(action, resp).await?.text().await?;
+    serde_json::from_str(&body).map_err(|e| {
+        // a failed request may only contain `result`, or a captcha page is served instead of json
+        if let Ok(ServiceResp { result }) = serde_json::from_str::<ServiceResp>(&body) {
+            BingError::from_result(action, &result)
+        } else if body.to_lowercase().contains("captcha") {
+            BingError::CaptchaChallenge {
+                action,
+                message: None,
+            }
+        } else {
+            BingError::Json(e)
+        }
+    })
+}
+
+fn proxy_error(e: reqwest::Error) -> BingError {
+    BingError::Proxy(e.to_string())
+}
+
+fn with_proxy_auth(proxy: Proxy, proxy_config: &ProxyConfig) -> Proxy {
+    match &proxy_config.auth {
+        Some(auth) => proxy.basic_auth(&auth.username, &auth.password),
+        None => proxy,
+    }
+}
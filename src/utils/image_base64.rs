@@ -1,9 +1,14 @@
 use base64::{engine::general_purpose::STANDARD, Engine};
 use image::codecs::jpeg::JpegEncoder;
 use image::DynamicImage;
+use reqwest::{header::LOCATION, Client as ReqwestClient, Response};
 use std::io::Cursor;
 use tokio::{fs::File, io::AsyncReadExt};
 
+use crate::types::error_type::BingError;
+
+use super::http_client::check_status;
+
 #[derive(Debug)]
 pub enum Image {
     Path(String),
@@ -11,7 +16,7 @@ pub enum Image {
     Base64(String),
 }
 
-async fn compress_image(img: DynamicImage) -> Result<String, anyhow::Error> {
+async fn compress_image(img: DynamicImage) -> Result<String, BingError> {
     let mut quality = 80;
     let mut buffer = Cursor::new(Vec::new());
     let target_size = 1000 * 1024; // 目标大小（字节）
@@ -33,8 +38,37 @@ async fn compress_image(img: DynamicImage) -> Result<String, anyhow::Error> {
     Ok(encoded)
 }
 
+const DOWNLOAD_ACTION: &str = "Download Image";
+
+async fn download(client: &ReqwestClient, url: &str) -> Result<Response, BingError> {
+    let mut url = url.to_string();
+    for _ in 0..10 {
+        let response = client.get(&url).send().await?;
+        if !response.status().is_redirection() {
+            return Ok(response);
+        }
+        let location = match response.headers().get(LOCATION) {
+            Some(location) => location.to_str()?,
+            None => return Ok(response),
+        };
+        url = response
+            .url()
+            .join(location)
+            .map_err(|e| BingError::InvalidHeader(format!("Location: {location}; {e}")))?
+            .to_string();
+    }
+    Err(BingError::Http {
+        action: DOWNLOAD_ACTION,
+        status: 310,
+        body: "Too many redirects".to_string(),
+    })
+}
+
 impl Image {
-    pub async fn to_base64(&self) -> Result<String, anyhow::Error> {
+    // `client` downloads `Image::Url` and should not follow redirects. They are followed here
+    // one by one, so the cookie jar of `client` picks the cookies of every host:
+    // the bing cookies only go to bing hosts, other hosts only get their own cookies
+    pub async fn to_base64(&self, client: &ReqwestClient) -> Result<String, BingError> {
         match self {
             Image::Path(path) => {
                 let mut file = File::open(path).await?;
@@ -44,7 +78,7 @@ impl Image {
                 compress_image(img).await
             }
             Image::Url(url) => {
-                let response = reqwest::get(url).await?;
+                let response = check_status(DOWNLOAD_ACTION, download(client, url).await?).await?;
                 let contents = response.bytes().await?;
                 let img = image::load_from_memory(&contents)?;
                 compress_image(img).await
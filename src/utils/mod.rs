@@ -1,8 +1,14 @@
+pub mod answer_render;
+pub mod citation;
+pub mod connect_ws;
+pub mod cookie_import;
+pub mod cookie_jar;
 pub mod cookie_pre;
 pub mod image_base64;
 pub mod msg_proces;
 pub mod process_bot_resp;
 pub mod draw_image;
+pub mod http_client;
 pub mod process_chat_msgs;
 pub mod stop_signal;
 pub mod suno;
\ No newline at end of file
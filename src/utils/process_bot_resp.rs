@@ -1,31 +1,129 @@
-use serde_json::{json, Value};
+use std::collections::HashSet;
+
+use serde_json::Value;
 
 use crate::{
-    types::bot_easy_resp_type::{BotResp, Image, Limit, SourceAttribution},
+    types::{
+        bot_easy_resp_type::{
+            BotResp, GeneratedCode, Image, Limit, SourceAttribution, TextDelta, WebResult,
+            Withdrawal,
+        },
+        client_config_type::TextMode,
+        error_type::BingError,
+    },
     BingClient,
 };
 
+use super::citation::cite;
 use super::draw_image::{gen_pool_image_url, gen_update_draw_conversation, poll_images};
 
+// The images of a `GenerateContentQuery` and the update of the conversation that shows them
+pub(crate) type DrawTask = tokio::task::JoinHandle<Result<(BotResp, Value), BingError>>;
+
+// The responses of a type 1 frame and the errors of its messages that could not be handled
 pub(crate) fn json2bot_resp_type1(
     json: &Value,
-    tasks_handle: &mut Vec<tokio::task::JoinHandle<(BotResp, Value)>>,
+    tasks_handle: &mut Vec<DrawTask>,
     client: &BingClient,
-) -> Vec<BotResp> {
+) -> (Vec<BotResp>, Vec<BingError>) {
     let mut bot_resps: Vec<BotResp> = Vec::new();
+    let mut errors = Vec::new();
     if let Value::Array(args) = json["arguments"].to_owned() {
         for arg in &args {
             if let Value::Array(messages) = arg["messages"].to_owned() {
                 for message in messages {
                     if let Value::String(text) = &message["text"] {
-                        let _ =
-                            process_text_msg(text, &message, &mut bot_resps, tasks_handle, client);
+                        if let Err(e) =
+                            process_text_msg(text, &message, &mut bot_resps, tasks_handle, client)
+                        {
+                            errors.push(e);
+                        }
                     }
                 }
             }
         }
     }
-    bot_resps
+    (bot_resps, errors)
+}
+
+// Turns the cumulative `BotResp::Text` of type 1 frames into what `mode` asks for
+#[derive(Default)]
+pub(crate) struct TextTracker {
+    text: String,
+    // the `messageId`s whose withdrawal was sent, later frames repeat it
+    withdrawn: HashSet<String>,
+}
+
+impl TextTracker {
+    pub(crate) fn track(&mut self, resps: Vec<BotResp>, mode: TextMode) -> Vec<BotResp> {
+        let mut tracked = Vec::with_capacity(resps.len());
+        for resp in resps {
+            let text = match resp {
+                BotResp::Text(text) => text,
+                BotResp::Retracted(withdrawal) => {
+                    if let Some(withdrawal) = self.withdraw(withdrawal) {
+                        tracked.push(BotResp::Retracted(withdrawal));
+                    }
+                    continue;
+                }
+                BotResp::Moderated(withdrawal) => {
+                    if let Some(withdrawal) = self.withdraw(withdrawal) {
+                        tracked.push(BotResp::Moderated(withdrawal));
+                    }
+                    continue;
+                }
+                resp => {
+                    tracked.push(resp);
+                    continue;
+                }
+            };
+            let delta = self.delta(&text);
+            if let (Some(delta), TextMode::Delta | TextMode::Both) = (delta, mode) {
+                tracked.push(BotResp::TextDelta(delta));
+            }
+            if mode != TextMode::Delta {
+                tracked.push(BotResp::Text(text));
+            }
+        }
+        tracked
+    }
+
+    // The whole answer so far
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    // Fills in the streamed text and starts over, `None` if the message was already withdrawn
+    fn withdraw(&mut self, mut withdrawal: Withdrawal) -> Option<Withdrawal> {
+        let message_id = withdrawal.message_id.clone().unwrap_or_default();
+        if !self.withdrawn.insert(message_id) {
+            return None;
+        }
+        withdrawal.text = std::mem::take(&mut self.text);
+        Some(withdrawal)
+    }
+
+    // `None` if the text did not change
+    fn delta(&mut self, text: &str) -> Option<TextDelta> {
+        if text == self.text {
+            return None;
+        }
+        let offset = if text.starts_with(&self.text) {
+            self.text.len()
+        } else {
+            // bing rewrote earlier text, the delta starts at the first differing char
+            self.text
+                .char_indices()
+                .zip(text.chars())
+                .find(|((_, old), new)| old != new)
+                .map_or(self.text.len().min(text.len()), |((index, _), _)| index)
+        };
+        self.text = text.to_string();
+        Some(TextDelta {
+            offset,
+            text: text[offset..].to_string(),
+        })
+    }
 }
 
 pub fn process_suggested_responses(suggested_responses: Vec<Value>) -> Option<Vec<String>> {
@@ -42,35 +140,38 @@ pub fn process_suggested_responses(suggested_responses: Vec<Value>) -> Option<Ve
     }
 }
 
+// One entry of `sourceAttributions`, an entry that does not parse is empty
+// so the `[^n^]` markers still point at the right one
+pub(crate) fn process_source(source: &Value) -> SourceAttribution {
+    let image = source
+        .get("imageLink")
+        .and_then(|v| v.as_str())
+        .map(|image_link| Image {
+            name: "bing_source_image.jpg".to_string(),
+            url: image_link.to_string(),
+        });
+    let mut source_resp =
+        serde_json::from_value::<SourceAttribution>(source.to_owned()).unwrap_or_default();
+    source_resp.image = image;
+    source_resp
+}
+
 pub(crate) fn process_source_msg(sources: Vec<Value>) -> Option<Vec<SourceAttribution>> {
     let mut source_resps = Vec::new();
     for source in sources {
-        let image = source
-            .get("imageLink")
-            .and_then(|v| v.as_str())
-            .map(|image_link| Image {
-                name: "bing_source_image.jpg".to_string(),
-                url: image_link.to_string(),
-            });
-
-        match serde_json::from_value::<SourceAttribution>(source) {
-            Ok(mut source_resp) => {
-                source_resp.image = image;
-                if let Some(url) = &source_resp.see_more_url {
-                    if url.starts_with("https://aefd.nelreports.net")
-                        || url.starts_with("https://www.bing.com/search?q=")
-                    {
-                        continue;
-                    }
-                }
-                if source_resp.display_name.is_some()
-                    || source_resp.see_more_url.is_some()
-                    || source_resp.image.is_some()
-                {
-                    source_resps.push(source_resp);
-                }
+        let source_resp = process_source(&source);
+        if let Some(url) = &source_resp.see_more_url {
+            if url.starts_with("https://aefd.nelreports.net")
+                || url.starts_with("https://www.bing.com/search?q=")
+            {
+                continue;
             }
-            Err(_) => return None,
+        }
+        if source_resp.display_name.is_some()
+            || source_resp.see_more_url.is_some()
+            || source_resp.image.is_some()
+        {
+            source_resps.push(source_resp);
         }
     }
     if !source_resps.is_empty() {
@@ -84,73 +185,240 @@ fn process_text_msg(
     text: &str,
     message: &Value,
     bot_resps: &mut Vec<BotResp>,
-    botresp_tasks: &mut Vec<tokio::task::JoinHandle<(BotResp, Value)>>,
+    botresp_tasks: &mut Vec<DrawTask>,
     client: &BingClient,
-) -> Result<(), anyhow::Error> {
+) -> Result<(), BingError> {
+    if let Some(withdrawal) = process_withdrawal(message) {
+        bot_resps.push(withdrawal);
+    }
     if let Some(content_origin) = message["contentOrigin"].as_str() {
         if content_origin == "Apology" {
             bot_resps.push(BotResp::Apology(text.to_owned()));
             return Ok(());
         }
     }
-    let message_id;
-    if let Some(Value::String(id)) = message.get("messageId") {
-        message_id = id.clone()
-    } else {
-        return Ok(());
-    }
+    // only a drawing needs the id
+    let message_id = message.get("messageId").and_then(|v| v.as_str());
     match message.get("messageType").and_then(|v| v.as_str()) {
-        Some("GenerateContentQuery") => match message.get("contentType").and_then(|v| v.as_str()) {
-            Some("IMAGE") => {
+        Some("GenerateContentQuery") => match (
+            message.get("contentType").and_then(|v| v.as_str()),
+            message_id,
+        ) {
+            (Some("IMAGE"), Some(message_id)) => {
+                let message_id = message_id.to_owned();
                 let prompt = text.to_owned();
-                let headers = client.gen_header()?;
+                let headers = client
+                    .gen_header()
+                    .map_err(|e| BingError::AnswerImage(Box::new(e)))?;
+                let endpoints = client.config.endpoints.clone();
+                // cheap clones, the connection pools are shared
+                let reqwest_client = client.reqwest_client.clone();
+                let no_redirect_client = client.no_redirect_client.clone();
 
                 botresp_tasks.push(tokio::spawn(async move {
-                    match gen_pool_image_url(&prompt, headers.clone(), &message_id).await {
-                        Ok(url) => match poll_images(url.clone(), headers, true).await {
-                            Ok(imgs) => {
-                                let resps = BotResp::Image(imgs);
-                                (
-                                    resps,
-                                    gen_update_draw_conversation(&message_id, &prompt, &url),
-                                )
-                            }
-                            Err(e) => {
-                                return (
-                                    BotResp::Apology(format!(
-                                        "Bing Copilot Draw Image Failed; Error Message: {}",
-                                        e
-                                    )),
-                                    json!({}),
-                                )
-                            }
-                        },
-                        Err(e) => {
-                            return (
-                                BotResp::Apology(format!(
-                                    "Bing Copilot Draw Image Failed; Error Message: {e}",
-                                )),
-                                json!({}),
-                            );
-                        }
-                    }
+                    let url = gen_pool_image_url(
+                        &no_redirect_client,
+                        &endpoints,
+                        &prompt,
+                        headers,
+                        &message_id,
+                    )
+                    .await?;
+                    let imgs = poll_images(&reqwest_client, &url, true).await?;
+                    Ok((
+                        BotResp::Image(imgs),
+                        gen_update_draw_conversation(&message_id, &prompt, &url),
+                    ))
                 }));
             }
-            Some("SUNO") => {
-                // todo
-            }
-            _ => {}
+            (Some("SUNO"), _) => bot_resps.push(BotResp::SongPrompt(text.to_owned())),
+            _ => bot_resps.push(BotResp::Other {
+                message_type: "GenerateContentQuery".to_owned(),
+                text: text.to_owned(),
+            }),
         },
         Some("InternalLoaderMessage") => bot_resps.push(BotResp::Notice(text.to_owned())),
-        Some("InternalSearchResult") => {}
-        Some(_) => {}
+        Some("Disengaged") => bot_resps.push(BotResp::Disengaged(text.to_owned())),
+        Some("Progress") => bot_resps.push(BotResp::Progress(text.to_owned())),
+        Some("InternalTasksMessage") => {
+            let tasks = process_tasks(text, message);
+            if !tasks.is_empty() {
+                bot_resps.push(BotResp::Tasks(tasks))
+            }
+        }
+        Some("GeneratedCode") => {
+            bot_resps.push(BotResp::GeneratedCode(process_generated_code(text, message)))
+        }
+        Some("RenderCardRequest") => bot_resps.push(BotResp::RenderCard(text.to_owned())),
+        Some("RenderContentRequest") => bot_resps.push(BotResp::RenderContent(text.to_owned())),
+        Some("Disclaimer") => bot_resps.push(BotResp::Disclaimer(text.to_owned())),
+        Some("ActionRequest") => bot_resps.push(BotResp::ActionRequest(text.to_owned())),
+        Some("ConfirmationCard") => bot_resps.push(BotResp::ConfirmationCard(text.to_owned())),
+        Some("Context") => bot_resps.push(BotResp::Context(text.to_owned())),
+        Some("AdsQuery") => bot_resps.push(BotResp::AdsQuery(text.to_owned())),
+        Some("SemanticSerp") => bot_resps.push(BotResp::SemanticSerp(text.to_owned())),
+        Some("Chat") => bot_resps.push(BotResp::Text(text.to_owned())),
+        Some("InternalSearchQuery" | "SearchQuery") => {
+            bot_resps.push(BotResp::SearchQuery(process_search_query(text, message)))
+        }
+        Some("InternalSearchResult") => {
+            let results = process_search_results(message);
+            if !results.is_empty() {
+                bot_resps.push(BotResp::SearchResults(results))
+            }
+        }
+        Some(message_type) => bot_resps.push(BotResp::Other {
+            message_type: message_type.to_owned(),
+            text: text.to_owned(),
+        }),
         None => bot_resps.push(BotResp::Text(text.to_owned())),
     }
     Ok(())
 }
 
-pub(crate) fn json2bot_resp_type2(json: &Value) -> Result<Vec<BotResp>, String> {
+// An answer message flagged by `offense`, replaced by an apology, or whose text was moved
+// to `hiddenText`. The withdrawn text is filled in by `TextTracker`.
+pub(crate) fn process_withdrawal(message: &Value) -> Option<BotResp> {
+    if message.get("messageType").is_some() {
+        return None;
+    }
+    let field = |name: &str| {
+        message
+            .get(name)
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_owned())
+    };
+    let withdrawal = Withdrawal {
+        text: String::new(),
+        message_id: field("messageId"),
+        offense: field("offense"),
+        content_origin: field("contentOrigin"),
+        hidden_text: field("hiddenText"),
+    };
+    if withdrawal
+        .offense
+        .as_deref()
+        .is_some_and(|offense| offense != "None" && offense != "Unknown")
+    {
+        Some(BotResp::Moderated(withdrawal))
+    } else if withdrawal.content_origin.as_deref() == Some("Apology")
+        || withdrawal.hidden_text.is_some()
+    {
+        Some(BotResp::Retracted(withdrawal))
+    } else {
+        None
+    }
+}
+
+// `tasks` is a list of strings or of objects with a `text`, otherwise one task per line
+fn process_tasks(text: &str, message: &Value) -> Vec<String> {
+    if let Some(tasks) = message.get("tasks").and_then(|v| v.as_array()) {
+        return tasks
+            .iter()
+            .filter_map(|task| task.as_str().or_else(|| task.get("text")?.as_str()))
+            .map(|task| task.to_owned())
+            .collect();
+    }
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_owned())
+        .collect()
+}
+
+// The code is either bare with the language in `language`, or a fenced markdown block
+fn process_generated_code(text: &str, message: &Value) -> GeneratedCode {
+    let mut language = message
+        .get("language")
+        .or_else(|| message.get("codeLanguage"))
+        .and_then(|v| v.as_str())
+        .map(|language| language.to_owned());
+    let mut code = text.trim();
+    if let Some(fenced) = code.strip_prefix("```") {
+        let (info, body) = fenced.split_once('\n').unwrap_or((fenced, ""));
+        if language.is_none() && !info.trim().is_empty() {
+            language = Some(info.trim().to_owned());
+        }
+        code = body.trim_end().strip_suffix("```").unwrap_or(body).trim_end();
+    }
+    GeneratedCode {
+        language,
+        code: code.to_owned(),
+    }
+}
+
+// `hiddenText` holds the bare query, `text` is "Searching the web for: `query`"
+fn process_search_query(text: &str, message: &Value) -> String {
+    if let Some(query) = message.get("hiddenText").and_then(|v| v.as_str()) {
+        if !query.is_empty() {
+            return query.to_owned();
+        }
+    }
+    let query = text.split_once(": ").map_or(text, |(_, query)| query);
+    query.trim().trim_matches('`').to_owned()
+}
+
+// The results are in `groundingInfo`, or as a json code block in `hiddenText`,
+// keyed by the kind of search, e.g. `web_search_results`
+pub(crate) fn process_search_results(message: &Value) -> Vec<WebResult> {
+    let grounding = match message.get("groundingInfo") {
+        Some(grounding) if grounding.is_object() => grounding.to_owned(),
+        _ => {
+            let hidden_text = message
+                .get("hiddenText")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            let json_text = hidden_text
+                .trim()
+                .trim_start_matches("```json")
+                .trim_end_matches("```");
+            match serde_json::from_str::<Value>(json_text) {
+                Ok(grounding) => grounding,
+                Err(_) => return Vec::new(),
+            }
+        }
+    };
+    let mut results = Vec::new();
+    let Some(kinds) = grounding.as_object() else {
+        return results;
+    };
+    for items in kinds.values().filter_map(|v| v.as_array()) {
+        for item in items {
+            let Some(url) = item.get("url").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let snippet = match item.get("snippets") {
+                Some(Value::Array(snippets)) => snippets
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                _ => item
+                    .get("snippet")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_owned(),
+            };
+            results.push(WebResult {
+                title: item
+                    .get("title")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_owned(),
+                url: url.to_owned(),
+                snippet,
+            });
+        }
+    }
+    results
+}
+
+// The responses of the final frame and why a part of it was skipped, e.g. a `throttling`
+// that could not be parsed. `Err` only without an item.
+pub(crate) fn json2bot_resp_type2(json: &Value) -> Result<(Vec<BotResp>, Vec<String>), String> {
     let mut bot_resps: Vec<BotResp> = Vec::new();
+    let mut skipped = Vec::new();
     match json.get("item").and_then(|v| v.as_object()) {
         Some(item) => {
             if let Some(messages) = item.get("messages").and_then(|v| v.as_array()) {
@@ -158,12 +426,24 @@ pub(crate) fn json2bot_resp_type2(json: &Value) -> Result<Vec<BotResp>, String>
                     if let Some(author) = message.get("author").and_then(|v| v.as_str()) {
                         match author {
                             "bot" => {
+                                if let Some(withdrawal) = process_withdrawal(message) {
+                                    bot_resps.push(withdrawal);
+                                }
                                 if let Some(sources) =
                                     message.get("sourceAttributions").and_then(|v| v.as_array())
                                 {
                                     if let Some(sources) = process_source_msg(sources.to_owned()) {
                                         bot_resps.push(BotResp::SourceAttribution(sources))
                                     }
+                                    // the answer itself, not a search or loader message
+                                    if let (Some(text), None) = (
+                                        message.get("text").and_then(|v| v.as_str()),
+                                        message.get("messageType"),
+                                    ) {
+                                        let sources: Vec<SourceAttribution> =
+                                            sources.iter().map(process_source).collect();
+                                        bot_resps.push(BotResp::Citations(cite(text, &sources)))
+                                    }
                                 }
                                 if let Some(suggested_responses) =
                                     message.get("suggestedResponses").and_then(|v| v.as_array())
@@ -185,11 +465,11 @@ pub(crate) fn json2bot_resp_type2(json: &Value) -> Result<Vec<BotResp>, String>
                 let limit_str = throttling.to_string();
                 match serde_json::from_str::<Limit>(&limit_str) {
                     Ok(limit) => bot_resps.push(BotResp::Limit(limit)),
-                    Err(e) => return Err(format!("Failed to parse limit: {}", e)),
+                    Err(e) => skipped.push(format!("Failed to parse limit: {}", e)),
                 }
             }
         }
         None => return Err("Item object not found in JSON".to_string()),
     }
-    Ok(bot_resps)
+    Ok((bot_resps, skipped))
 }
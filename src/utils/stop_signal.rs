@@ -1,23 +1,99 @@
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+use tokio::sync::{mpsc, oneshot};
+
+use crate::types::stop_outcome_type::{StopOutcome, StopStatus};
+
+// A stop request, with a sender for the outcome if the caller waits for it
+type StopRequest = Option<oneshot::Sender<StopOutcome>>;
+
+// The receiving side, owned by the task reading the answer
 pub struct StopSignal {
     stop: Arc<AtomicBool>,
+    text: Arc<Mutex<String>>,
+    tx: mpsc::UnboundedSender<StopRequest>,
+    rx: mpsc::UnboundedReceiver<StopRequest>,
+}
+
+// Stops the answer of an `AskStream`, it can be cloned and sent to other tasks
+#[derive(Debug, Clone)]
+pub struct StopHandle {
+    stop: Arc<AtomicBool>,
+    text: Arc<Mutex<String>>,
+    tx: mpsc::UnboundedSender<StopRequest>,
+}
+
+impl Default for StopSignal {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl StopSignal {
     pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
         StopSignal {
             stop: Arc::new(AtomicBool::new(false)),
+            text: Arc::new(Mutex::new(String::new())),
+            tx,
+            rx,
         }
     }
     pub fn check_stop(&self) -> bool {
         self.stop.load(Ordering::SeqCst)
     }
+    pub fn handle(&self) -> StopHandle {
+        StopHandle {
+            stop: self.stop.clone(),
+            text: self.text.clone(),
+            tx: self.tx.clone(),
+        }
+    }
+    // Requests a stop without waiting for the outcome
     pub fn stop_fn(&self) -> impl Fn() {
-        let stop_clone = self.stop.clone();
+        let handle = self.handle();
         move || {
-            stop_clone.store(true, Ordering::SeqCst);
+            handle.request(None);
         }
     }
+    // Waits for the next stop request, never resolves if there can be none
+    pub(crate) async fn recv(&mut self) -> StopRequest {
+        match self.rx.recv().await {
+            Some(request) => request,
+            None => std::future::pending().await,
+        }
+    }
+    pub(crate) fn set_text(&self, text: &str) {
+        let mut current = self.text.lock().unwrap();
+        current.clear();
+        current.push_str(text);
+    }
+    pub(crate) fn text(&self) -> String {
+        self.text.lock().unwrap().clone()
+    }
+}
+
+impl StopHandle {
+    // Sends the `stop` invocation right away and waits until bing confirms it,
+    // `ClientConfig.stop_timeout` passes or the answer ends
+    pub async fn stop(&self) -> StopOutcome {
+        let (tx, rx) = oneshot::channel();
+        self.request(Some(tx));
+        match rx.await {
+            Ok(outcome) => outcome,
+            // the answer had already ended
+            Err(_) => StopOutcome {
+                status: StopStatus::Finished,
+                text: self.text.lock().unwrap().clone(),
+            },
+        }
+    }
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+    fn request(&self, request: StopRequest) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.tx.send(request);
+    }
 }